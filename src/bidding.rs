@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use alloy::primitives::{Address, U256};
+
+use crate::socketio::model::{DaLayer, PostingIntent};
+
+/// Estimated cost of posting an intent on each DA layer, in units of its reward token
+pub type DaCosts = HashMap<DaLayer, U256>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bid {
+    Accept(DaLayer),
+    Reject(String),
+}
+
+/// Decides whether to express interest in a posting intent, and on which DA layer
+pub trait BiddingPolicy: Send + Sync {
+    fn bid(&self, intent: &PostingIntent, costs: &DaCosts) -> Bid;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BiddingConfig {
+    /// Minimum reward, in reward token units, per byte of data
+    pub min_reward_per_byte: U256,
+    /// Maximum size in bytes of the data we are willing to post
+    pub max_size: Option<u64>,
+    /// If not empty, only intents rewarding in these tokens are accepted
+    pub token_allowlist: Vec<Address>,
+    /// Intents rewarding in these tokens are always rejected
+    pub token_denylist: Vec<Address>,
+    /// DA layers we post to, most preferred first. If empty, the client's order is used
+    pub da_layer_preference: Vec<DaLayer>,
+    /// Whether to bid on DA layers whose cost could not be estimated instead of skipping them
+    pub bid_without_cost_estimate: bool,
+}
+
+pub struct DefaultBiddingPolicy {
+    config: BiddingConfig,
+}
+
+impl DefaultBiddingPolicy {
+    pub fn new(config: BiddingConfig) -> Self {
+        Self { config }
+    }
+
+    fn candidate_layers(&self, intent: &PostingIntent) -> Vec<DaLayer> {
+        if self.config.da_layer_preference.is_empty() {
+            return intent.acceptable_da_layers.clone();
+        }
+        self.config
+            .da_layer_preference
+            .iter()
+            .filter(|da_layer| intent.acceptable_da_layers.contains(da_layer))
+            .copied()
+            .collect()
+    }
+}
+
+impl BiddingPolicy for DefaultBiddingPolicy {
+    fn bid(&self, intent: &PostingIntent, costs: &DaCosts) -> Bid {
+        if let Some(max_size) = self.config.max_size {
+            if intent.size > max_size {
+                return Bid::Reject(format!(
+                    "Size {} exceeds maximum size {max_size}",
+                    intent.size
+                ));
+            }
+        }
+
+        if self.config.token_denylist.contains(&intent.reward_token) {
+            return Bid::Reject(format!("Reward token {} is denied", intent.reward_token));
+        }
+        if !self.config.token_allowlist.is_empty()
+            && !self.config.token_allowlist.contains(&intent.reward_token)
+        {
+            return Bid::Reject(format!(
+                "Reward token {} is not allowed",
+                intent.reward_token
+            ));
+        }
+
+        let min_reward = self
+            .config
+            .min_reward_per_byte
+            .saturating_mul(U256::from(intent.size));
+        if intent.reward_amount < min_reward {
+            return Bid::Reject(format!(
+                "Reward {} is less than minimum reward {min_reward}",
+                intent.reward_amount
+            ));
+        }
+
+        let candidates = self.candidate_layers(intent);
+        if candidates.is_empty() {
+            return Bid::Reject("No acceptable DA layer".to_string());
+        }
+
+        candidates
+            .into_iter()
+            .find(|da_layer| {
+                costs
+                    .get(da_layer)
+                    .map_or(self.config.bid_without_cost_estimate, |cost| {
+                        *cost <= intent.reward_amount
+                    })
+            })
+            .map_or_else(
+                || Bid::Reject("Reward does not cover the DA cost".to_string()),
+                Bid::Accept,
+            )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn intent(size: u64, reward_amount: u64, reward_token: Address) -> PostingIntent {
+        PostingIntent {
+            task_id: Uuid::new_v4(),
+            size,
+            client_address: Address::ZERO,
+            reward_amount: U256::from(reward_amount),
+            reward_token,
+            acceptable_da_layers: vec![DaLayer::Celestia, DaLayer::Eip4844],
+        }
+    }
+
+    fn free_costs() -> DaCosts {
+        DaCosts::from([
+            (DaLayer::Celestia, U256::ZERO),
+            (DaLayer::Eip4844, U256::ZERO),
+        ])
+    }
+
+    #[test]
+    fn test_default_policy_accepts_everything() {
+        let policy = DefaultBiddingPolicy::new(BiddingConfig::default());
+        let bid = policy.bid(&intent(100, 0, Address::ZERO), &free_costs());
+        assert_eq!(bid, Bid::Accept(DaLayer::Celestia));
+    }
+
+    #[test]
+    fn test_default_policy_filters() {
+        let allowed = Address::repeat_byte(1);
+        let denied = Address::repeat_byte(2);
+        let policy = DefaultBiddingPolicy::new(BiddingConfig {
+            min_reward_per_byte: U256::from(2),
+            max_size: Some(1000),
+            token_allowlist: vec![allowed],
+            token_denylist: vec![denied],
+            da_layer_preference: vec![DaLayer::Eip4844, DaLayer::Celestia],
+            ..Default::default()
+        });
+        let costs = free_costs();
+
+        assert!(matches!(
+            policy.bid(&intent(1001, 10_000, allowed), &costs),
+            Bid::Reject(_)
+        ));
+        assert!(matches!(
+            policy.bid(&intent(100, 10_000, denied), &costs),
+            Bid::Reject(_)
+        ));
+        assert!(matches!(
+            policy.bid(&intent(100, 10_000, Address::ZERO), &costs),
+            Bid::Reject(_)
+        ));
+        assert!(matches!(
+            policy.bid(&intent(100, 199, allowed), &costs),
+            Bid::Reject(_)
+        ));
+        assert_eq!(
+            policy.bid(&intent(100, 200, allowed), &costs),
+            Bid::Accept(DaLayer::Eip4844)
+        );
+    }
+
+    #[test]
+    fn test_default_policy_skips_unprofitable_layers() {
+        let policy = DefaultBiddingPolicy::new(BiddingConfig::default());
+        let costs = DaCosts::from([
+            (DaLayer::Celestia, U256::from(150)),
+            (DaLayer::Eip4844, U256::from(50)),
+        ]);
+        assert_eq!(
+            policy.bid(&intent(100, 100, Address::ZERO), &costs),
+            Bid::Accept(DaLayer::Eip4844)
+        );
+
+        let costs = DaCosts::from([
+            (DaLayer::Celestia, U256::from(150)),
+            (DaLayer::Eip4844, U256::from(150)),
+        ]);
+        assert!(matches!(
+            policy.bid(&intent(100, 100, Address::ZERO), &costs),
+            Bid::Reject(_)
+        ));
+    }
+
+    #[test]
+    fn test_default_policy_without_cost_estimate() {
+        let costs = DaCosts::from([(DaLayer::Eip4844, U256::from(50))]);
+        let policy = DefaultBiddingPolicy::new(BiddingConfig::default());
+        assert_eq!(
+            policy.bid(&intent(100, 100, Address::ZERO), &costs),
+            Bid::Accept(DaLayer::Eip4844)
+        );
+        assert!(matches!(
+            policy.bid(&intent(100, 100, Address::ZERO), &DaCosts::new()),
+            Bid::Reject(_)
+        ));
+
+        let policy = DefaultBiddingPolicy::new(BiddingConfig {
+            bid_without_cost_estimate: true,
+            ..Default::default()
+        });
+        assert_eq!(
+            policy.bid(&intent(100, 100, Address::ZERO), &costs),
+            Bid::Accept(DaLayer::Celestia)
+        );
+    }
+}
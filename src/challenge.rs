@@ -0,0 +1,330 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, FixedBytes, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use eyre::OptionExt;
+use metrics::counter;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    contracts::kuda::Kuda::KudaInstance,
+    da::registry::DaRegistry,
+    journal::{BlockCursor, Journal, TaskEvent, TaskStage},
+};
+
+/// Most blocks queried for events at once, as providers limit the range of `eth_getLogs`
+const MAX_BLOCK_RANGE: u64 = 2_000;
+
+/// What the responder does about a challenge raised against one of our tasks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Response {
+    /// The challenge was countered already, or the task is settled
+    Skip,
+    /// The challenge is journaled, only the counter challenge is missing
+    Counter,
+    /// The challenge is new to us, including on tasks that failed once their receipt was sent
+    RecordAndCounter,
+}
+
+impl Response {
+    fn for_stage(stage: TaskStage) -> Self {
+        match stage {
+            TaskStage::CounterChallenged | TaskStage::Settled => Response::Skip,
+            TaskStage::Challenged => Response::Counter,
+            _ => Response::RecordAndCounter,
+        }
+    }
+}
+
+pub struct ChallengeResponder<T: Transport + Clone, P: Provider<T>> {
+    pub operator_address: Address,
+    pub kuda_instance: Arc<KudaInstance<T, P>>,
+    pub da_registry: Arc<DaRegistry>,
+    pub journal: Arc<Journal>,
+    /// Block to resume watching `ChallengeCreated` events from after a restart
+    pub cursor: BlockCursor,
+    pub poll_interval: Duration,
+}
+
+impl<T: Transport + Clone, P: Provider<T>> ChallengeResponder<T, P> {
+    /// Watches `ChallengeCreated` events and counter-challenges the ones raised against us
+    pub async fn run(&self, cancellation_token: CancellationToken) -> eyre::Result<()> {
+        let counter_challenge_period = self
+            .kuda_instance
+            .COUNTER_CHALLENGE_PERIOD()
+            .call()
+            .await?
+            ._0;
+        let mut from_block = match self.cursor.load().await? {
+            Some(block) => block,
+            None => self.kuda_instance.provider().get_block_number().await?,
+        };
+        // Challenges we failed to counter before a restart. They are journaled, so they don't
+        // hold the cursor back
+        let mut pending = self
+            .journal
+            .open_challenges()
+            .await
+            .into_iter()
+            .filter(|(_, task)| task.stage == TaskStage::Challenged)
+            .map(|(task_id, _)| (FixedBytes(task_id.into_bytes()), u64::MAX))
+            .collect::<HashMap<_, _>>();
+
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    tracing::info!("Challenge responder cancelled");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+
+            from_block = self
+                .poll(from_block, counter_challenge_period, &mut pending)
+                .await;
+        }
+    }
+
+    /// Indexes the `ChallengeCreated` events from `from_block` into `pending`, then tries to
+    /// counter every pending challenge, keeping the ones that failed for the next poll. Pending
+    /// challenges are retried even if indexing failed. Returns the block to resume from
+    async fn poll(
+        &self,
+        from_block: u64,
+        counter_challenge_period: U256,
+        pending: &mut HashMap<FixedBytes<16>, u64>,
+    ) -> u64 {
+        let mut next_block = from_block;
+        if let Err(e) = self.index(&mut next_block, pending).await {
+            tracing::error!("Failed to query challenges from block {next_block}: {e:?}");
+        }
+
+        for task_id in pending.keys().copied().collect::<Vec<_>>() {
+            match self.respond(task_id, counter_challenge_period).await {
+                Ok(()) => {
+                    pending.remove(&task_id);
+                }
+                Err(e) => {
+                    counter!("counter_challenge_error").increment(1);
+                    tracing::error!(
+                        "Failed to counter challenge for task {}, retrying next poll: {e:?}",
+                        Uuid::from_bytes(task_id.0)
+                    );
+                }
+            }
+        }
+
+        // A pending challenge may not be journaled yet, so the cursor never moves past it
+        let cursor = pending.values().copied().fold(next_block, u64::min);
+        if let Err(e) = self.cursor.save(cursor).await {
+            tracing::error!("Failed to save challenge responder cursor: {e:?}");
+        }
+        next_block
+    }
+
+    /// Adds the tasks of the `ChallengeCreated` events from `next_block` up to the latest block
+    /// to `pending`, with the block they were challenged in. Queries at most
+    /// [`MAX_BLOCK_RANGE`] blocks at a time, moving `next_block` past every range queried
+    async fn index(
+        &self,
+        next_block: &mut u64,
+        pending: &mut HashMap<FixedBytes<16>, u64>,
+    ) -> eyre::Result<()> {
+        let latest_block = self.kuda_instance.provider().get_block_number().await?;
+        while *next_block <= latest_block {
+            let to_block = latest_block.min(*next_block + MAX_BLOCK_RANGE - 1);
+            let events = self
+                .kuda_instance
+                .ChallengeCreated_filter()
+                .from_block(*next_block)
+                .to_block(to_block)
+                .query()
+                .await?;
+            for (event, log) in events {
+                pending
+                    .entry(event.taskId)
+                    .or_insert(log.block_number.unwrap_or(*next_block));
+            }
+            *next_block = to_block + 1;
+        }
+        Ok(())
+    }
+
+    /// Counters the challenge of `task_id` if it was raised against us. Fails only if it can
+    /// be retried, giving up once the counter challenge period is over
+    #[tracing::instrument(skip(self))]
+    async fn respond(
+        &self,
+        task_id: FixedBytes<16>,
+        counter_challenge_period: U256,
+    ) -> eyre::Result<()> {
+        let challenge = self.kuda_instance.challengeData(task_id).call().await?;
+        if challenge.operator != self.operator_address {
+            return Ok(());
+        }
+
+        // The challenge is against us, so our receipt made it on-chain even if the task failed
+        // after it was sent
+        let task_id_uuid = Uuid::from_bytes(task_id.0);
+        let stage = self
+            .journal
+            .task(&task_id_uuid)
+            .await
+            .map(|task| task.stage)
+            .unwrap_or_default();
+        match Response::for_stage(stage) {
+            Response::Skip => return Ok(()),
+            Response::Counter => {}
+            Response::RecordAndCounter => {
+                tracing::warn!("Task {task_id_uuid} challenged by {}", challenge.challenger);
+                counter!("challenge_received").increment(1);
+                self.journal
+                    .record_or_log(
+                        task_id_uuid,
+                        TaskEvent::Challenged {
+                            challenger: challenge.challenger,
+                        },
+                    )
+                    .await;
+            }
+        }
+
+        let latest_block = self
+            .kuda_instance
+            .provider()
+            .get_block_by_number(BlockNumberOrTag::Latest, false)
+            .await?
+            .ok_or_eyre("Could not get latest block")?;
+        let deadline = challenge.timeStamp + counter_challenge_period;
+        let missed = if challenge.isNoOp {
+            Some("Cannot counter challenge a no-op task")
+        } else if U256::from(latest_block.header.timestamp) >= deadline {
+            Some("Counter challenge period is over")
+        } else {
+            None
+        };
+        if let Some(error) = missed {
+            counter!("counter_challenge_missed").increment(1);
+            tracing::error!("Giving up on the challenge of task {task_id_uuid}: {error}");
+            self.journal
+                .record_or_log(
+                    task_id_uuid,
+                    TaskEvent::Failed {
+                        error: error.to_string(),
+                    },
+                )
+                .await;
+            return Ok(());
+        }
+
+        let receipt = self
+            .journal
+            .receipt(&task_id_uuid)
+            .await
+            .ok_or_eyre("No receipt recorded for challenged task")?;
+        let proof = self
+            .da_registry
+            .backend_for(&receipt)?
+            .inclusion_proof(&receipt)
+            .await?;
+
+        let tx_receipt = self
+            .kuda_instance
+            .createCounterChallenge(task_id, proof)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        if !tx_receipt.status() {
+            return Err(eyre::eyre!(
+                "Counter challenge transaction {} reverted",
+                tx_receipt.transaction_hash
+            ));
+        }
+
+        counter!("counter_challenge_success").increment(1);
+        self.journal
+            .record_or_log(
+                task_id_uuid,
+                TaskEvent::CounterChallenged {
+                    tx_hash: tx_receipt.transaction_hash,
+                },
+            )
+            .await;
+        tracing::info!(
+            "Counter challenged task {task_id_uuid} on {} with tx hash: {}",
+            receipt.da_layer(),
+            tx_receipt.transaction_hash
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::providers::ProviderBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_response_for_stage() {
+        assert_eq!(
+            Response::for_stage(TaskStage::Failed),
+            Response::RecordAndCounter
+        );
+        assert_eq!(
+            Response::for_stage(TaskStage::ReceiptMined),
+            Response::RecordAndCounter
+        );
+        // Tasks missing from the journal
+        assert_eq!(
+            Response::for_stage(TaskStage::default()),
+            Response::RecordAndCounter
+        );
+        assert_eq!(
+            Response::for_stage(TaskStage::Challenged),
+            Response::Counter
+        );
+        assert_eq!(
+            Response::for_stage(TaskStage::CounterChallenged),
+            Response::Skip
+        );
+        assert_eq!(Response::for_stage(TaskStage::Settled), Response::Skip);
+    }
+
+    #[tokio::test]
+    async fn test_poll_keeps_pending_when_query_fails() {
+        let path = std::env::temp_dir().join(format!("kuda-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Arc::new(Journal::open(&path).await.unwrap());
+        // Nothing listens there, so every call fails
+        let provider = ProviderBuilder::new().on_http("http://127.0.0.1:1".parse().unwrap());
+        let responder = ChallengeResponder {
+            operator_address: Address::repeat_byte(1),
+            kuda_instance: Arc::new(KudaInstance::new(Address::ZERO, provider)),
+            da_registry: Arc::new(DaRegistry::default()),
+            cursor: journal.cursor("challenge"),
+            journal,
+            poll_interval: Duration::from_secs(1),
+        };
+        let mut pending =
+            HashMap::from([(FixedBytes([1; 16]), 5), (FixedBytes([2; 16]), u64::MAX)]);
+
+        assert_eq!(responder.poll(10, U256::ZERO, &mut pending).await, 10);
+        assert_eq!(pending.len(), 2);
+        assert_eq!(responder.cursor.load().await.unwrap(), Some(5));
+
+        pending.remove(&FixedBytes([1; 16]));
+        assert_eq!(responder.poll(10, U256::ZERO, &mut pending).await, 10);
+        assert_eq!(responder.cursor.load().await.unwrap(), Some(10));
+
+        tokio::fs::remove_file(path.with_extension("challenge.cursor"))
+            .await
+            .unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
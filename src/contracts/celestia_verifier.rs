@@ -0,0 +1,48 @@
+use alloy::sol;
+
+// Inclusion proof of the shares of a blob in the data root of a Celestia block, laid out like
+// Blobstream's `SharesProof` without the attestation, which the verifier checks on its side
+sol!(
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct Namespace {
+        bytes1 version;
+        bytes28 id;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct NamespaceNode {
+        Namespace min;
+        Namespace max;
+        bytes32 digest;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct NamespaceMerkleMultiproof {
+        uint256 beginKey;
+        uint256 endKey;
+        NamespaceNode[] sideNodes;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct BinaryMerkleProof {
+        bytes32[] sideNodes;
+        uint256 key;
+        uint256 numLeaves;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct SharesProof {
+        uint64 height;
+        bytes32 dataRoot;
+        bytes[] data;
+        NamespaceMerkleMultiproof[] shareProofs;
+        Namespace namespace;
+        NamespaceNode[] rowRoots;
+        BinaryMerkleProof[] rowProofs;
+    }
+);
@@ -0,0 +1,19 @@
+use alloy::sol;
+
+// Proof that a blob was published in a beacon block. The point evaluation fields are the input of
+// the EIP-4844 precompile, the inclusion proof ties the commitment to the block body root
+sol!(
+    #[allow(missing_docs)]
+    #[derive(Debug, PartialEq, Eq)]
+    struct BlobProof {
+        uint64 slot;
+        uint64 index;
+        bytes32 versionedHash;
+        bytes32 z;
+        bytes32 y;
+        bytes commitment;
+        bytes proof;
+        bytes32 bodyRoot;
+        bytes32[] commitmentInclusionProof;
+    }
+);
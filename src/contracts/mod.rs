@@ -1,4 +1,7 @@
+pub mod celestia_verifier;
 pub mod core;
+pub mod eip4844_verifier;
 pub mod erc20_mintable;
 pub mod kuda;
+pub mod price_feed;
 pub mod vault;
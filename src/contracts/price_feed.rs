@@ -0,0 +1,19 @@
+use alloy::sol;
+
+sol!(
+    #[allow(missing_docs)]
+    #[sol(rpc)]
+    interface PriceFeed {
+        function decimals() external view returns (uint8);
+        function latestRoundData()
+            external
+            view
+            returns (
+                uint80 roundId,
+                int256 answer,
+                uint256 startedAt,
+                uint256 updatedAt,
+                uint80 answeredInRound
+            );
+    }
+);
@@ -0,0 +1,201 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::{
+    primitives::{Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use metrics::counter;
+use tokio::sync::RwLock;
+
+use crate::{
+    bidding::DaCosts,
+    contracts::erc20_mintable::ERC20Mintable::ERC20MintableInstance,
+    da::registry::DaRegistry,
+    price::{convert, Asset, PriceSource},
+    socketio::model::{DaLayer, PostingIntent},
+};
+
+/// Estimates what posting an intent would cost us, in units of its reward token
+pub struct CostEstimator<T: Transport + Clone, P: Provider<T>> {
+    da_registry: Arc<DaRegistry>,
+    price_source: Arc<PriceSource>,
+    provider: Arc<P>,
+    token_decimals: RwLock<HashMap<Address, u8>>,
+    _transport: std::marker::PhantomData<T>,
+}
+
+impl<T: Transport + Clone, P: Provider<T>> CostEstimator<T, P> {
+    pub fn new(
+        da_registry: Arc<DaRegistry>,
+        price_source: Arc<PriceSource>,
+        provider: Arc<P>,
+    ) -> Self {
+        Self {
+            da_registry,
+            price_source,
+            provider,
+            token_decimals: RwLock::new(HashMap::new()),
+            _transport: std::marker::PhantomData,
+        }
+    }
+
+    /// Costs of the acceptable DA layers of `intent`. Layers that can't be estimated are left out,
+    /// and only bid on if the bidding policy allows it
+    pub async fn estimate(&self, intent: &PostingIntent) -> DaCosts {
+        let mut costs = DaCosts::new();
+        for da_layer in &intent.acceptable_da_layers {
+            match self
+                .estimate_layer(*da_layer, intent.size, intent.reward_token)
+                .await
+            {
+                Ok(cost) => {
+                    costs.insert(*da_layer, cost);
+                }
+                Err(e) => {
+                    counter!("da_cost_estimate_error", "da_layer" => da_layer.to_string())
+                        .increment(1);
+                    tracing::warn!(
+                        "Could not estimate {da_layer} cost for task {}: {e:?}",
+                        intent.task_id
+                    )
+                }
+            }
+        }
+        costs
+    }
+
+    async fn estimate_layer(
+        &self,
+        da_layer: DaLayer,
+        size: u64,
+        reward_token: Address,
+    ) -> eyre::Result<U256> {
+        let backend = self.da_registry.get(da_layer)?;
+        let native_cost = backend.estimate_cost(size).await?;
+        let native_asset = backend.native_asset();
+        let native_decimals = native_asset
+            .native_decimals()
+            .ok_or_else(|| eyre::eyre!("{native_asset} is not a native asset"))?;
+        let native_usd = self.price_source.usd_price(&native_asset).await?;
+        let reward_usd = self
+            .price_source
+            .usd_price(&Asset::Token(reward_token))
+            .await?;
+        let reward_decimals = self.token_decimals(reward_token).await?;
+
+        convert(
+            native_cost,
+            native_decimals,
+            native_usd,
+            reward_decimals,
+            reward_usd,
+        )
+    }
+
+    async fn token_decimals(&self, token: Address) -> eyre::Result<u8> {
+        if let Some(decimals) = self.token_decimals.read().await.get(&token) {
+            return Ok(*decimals);
+        }
+        let decimals = ERC20MintableInstance::new(token, self.provider.clone())
+            .decimals()
+            .call()
+            .await?
+            ._0;
+        self.token_decimals.write().await.insert(token, decimals);
+        Ok(decimals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        providers::{ProviderBuilder, RootProvider},
+        transports::http::{Client, Http},
+    };
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{da::registry::tests::FakeBackend, price::AssetPrice};
+
+    const TOKEN: Address = Address::repeat_byte(1);
+
+    fn estimator(
+        tia_usd: f64,
+        token_usd: f64,
+    ) -> CostEstimator<Http<Client>, RootProvider<Http<Client>>> {
+        let mut da_registry = DaRegistry::default();
+        da_registry.register(FakeBackend);
+        let price_source = PriceSource::from_prices(&[
+            AssetPrice {
+                asset: Asset::Tia,
+                usd: tia_usd,
+            },
+            AssetPrice {
+                asset: Asset::Token(TOKEN),
+                usd: token_usd,
+            },
+        ]);
+        // Nothing listens there, so decimals are only known for `TOKEN`
+        let provider = ProviderBuilder::new().on_http("http://127.0.0.1:1".parse().unwrap());
+        let estimator = CostEstimator::new(
+            Arc::new(da_registry),
+            Arc::new(price_source),
+            Arc::new(provider),
+        );
+        estimator
+            .token_decimals
+            .try_write()
+            .unwrap()
+            .insert(TOKEN, 6);
+        estimator
+    }
+
+    fn intent(size: u64, reward_token: Address) -> PostingIntent {
+        PostingIntent {
+            task_id: Uuid::new_v4(),
+            size,
+            client_address: Address::ZERO,
+            reward_amount: U256::ZERO,
+            reward_token,
+            acceptable_da_layers: vec![DaLayer::Celestia, DaLayer::Eip4844],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_estimate() {
+        // 2 TIA at $4 is 8 tokens at $1, and there is no EIP-4844 backend to estimate
+        let costs = estimator(4.0, 1.0)
+            .estimate(&intent(2_000_000, TOKEN))
+            .await;
+        assert_eq!(
+            costs,
+            DaCosts::from([(DaLayer::Celestia, U256::from(8_000_000))])
+        );
+
+        // 3 utia at $1 is 0.75 base units of a token at $4, which we can't bid below
+        let costs = estimator(1.0, 4.0).estimate(&intent(3, TOKEN)).await;
+        assert_eq!(costs[&DaLayer::Celestia], U256::from(1));
+
+        let costs = estimator(4.0, 1.0).estimate(&intent(0, TOKEN)).await;
+        assert_eq!(costs[&DaLayer::Celestia], U256::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_without_price() {
+        // A zero price would make every post look free or priceless
+        assert!(estimator(0.0, 1.0)
+            .estimate(&intent(100, TOKEN))
+            .await
+            .is_empty());
+        assert!(estimator(4.0, 0.0)
+            .estimate(&intent(100, TOKEN))
+            .await
+            .is_empty());
+        // Neither priced nor with known decimals
+        assert!(estimator(4.0, 1.0)
+            .estimate(&intent(100, Address::ZERO))
+            .await
+            .is_empty());
+    }
+}
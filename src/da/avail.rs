@@ -0,0 +1,800 @@
+use std::{str::FromStr, time::Duration};
+
+use alloy::{
+    primitives::{keccak256, Bytes, B256, U256, U64},
+    rpc::client::RpcClient,
+    sol_types::SolValue,
+    transports::http::{Client, Http},
+};
+use blake2::{digest::consts::U32, Blake2b, Blake2b512, Digest};
+use parity_scale_codec::{Decode, Encode, Input, Output};
+use schnorrkel::{ExpansionMode, Keypair, MiniSecretKey};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use tokio::{sync::Mutex, time::Instant};
+use url::Url;
+
+use crate::price::Asset;
+
+use super::{BlobData, Estimator, Health, Prover, Submitter, Verifier};
+
+/// Index of the `dataAvailability` pallet in the Avail runtime
+const DATA_AVAILABILITY_PALLET: u8 = 29;
+/// Index of `submit_data` in the `dataAvailability` pallet
+const SUBMIT_DATA_CALL: u8 = 1;
+/// Version byte of a signed v4 extrinsic
+const SIGNED_EXTRINSIC_V4: u8 = 0b1000_0100;
+/// Signing payloads longer than this are hashed before being signed
+const MAX_UNHASHED_PAYLOAD: usize = 256;
+/// Signing context of substrate sr25519 signatures
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+/// SS58 prefix of Avail addresses
+const SS58_PREFIX: u8 = 42;
+/// Runtime spec versions whose `submitData` call index and signed extensions match the
+/// extrinsics we build. A runtime upgrade may change either, so other versions are refused
+const SUPPORTED_SPEC_VERSIONS: &[u32] = &[39];
+/// Time between two polls of the finalized head while waiting for an extrinsic
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Where a `submitData` extrinsic was finalized, and the keccak hash of its data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvailReceipt {
+    pub block_hash: B256,
+    pub block_number: u64,
+    pub extrinsic_index: u32,
+    pub data_hash: B256,
+}
+
+impl AvailReceipt {
+    /// ABI-encoded `(blockHash, blockNumber, extrinsicIndex)` context passed to
+    /// `Kuda::submitReceipt`
+    pub fn context(&self) -> Bytes {
+        Bytes::copy_from_slice(
+            &(self.block_hash, self.block_number, self.extrinsic_index).abi_encode(),
+        )
+    }
+
+    /// Rebuilds a receipt from the hex data hash `commitment` and `context` of a
+    /// `Kuda::submitReceipt` call
+    pub fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self> {
+        let (block_hash, block_number, extrinsic_index) =
+            <(B256, u64, u32)>::abi_decode(context, true)?;
+        Ok(AvailReceipt {
+            block_hash,
+            block_number,
+            extrinsic_index,
+            data_hash: B256::from_str(commitment)?,
+        })
+    }
+}
+
+/// Mortality of a transaction. We send immortal ones, other operators may not
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Era {
+    Immortal,
+    Mortal(u16),
+}
+
+impl Encode for Era {
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            Era::Immortal => dest.push_byte(0),
+            Era::Mortal(era) => era.encode_to(dest),
+        }
+    }
+}
+
+impl Decode for Era {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+        match input.read_byte()? {
+            0 => Ok(Era::Immortal),
+            first => Ok(Era::Mortal(u16::from_le_bytes([first, input.read_byte()?]))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum MultiAddress {
+    Id([u8; 32]),
+    Index(#[codec(compact)] u32),
+    Raw(Vec<u8>),
+    Address32([u8; 32]),
+    Address20([u8; 20]),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum MultiSignature {
+    Ed25519([u8; 64]),
+    Sr25519([u8; 64]),
+    Ecdsa([u8; 65]),
+}
+
+/// The signed extensions of the Avail runtime carrying data: `CheckEra`, `CheckNonce`,
+/// `ChargeTransactionPayment` and `CheckAppId`
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SignedExtra {
+    pub era: Era,
+    #[codec(compact)]
+    pub nonce: u32,
+    #[codec(compact)]
+    pub tip: u128,
+    #[codec(compact)]
+    pub app_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SubmitDataCall {
+    pub pallet_index: u8,
+    pub call_index: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct SignedExtrinsic {
+    pub version: u8,
+    pub signer: MultiAddress,
+    pub signature: MultiSignature,
+    pub extra: SignedExtra,
+    pub call: SubmitDataCall,
+}
+
+impl SignedExtrinsic {
+    /// The extrinsic as submitted and stored in blocks, prefixed with its length
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encode().encode()
+    }
+
+    /// Decodes a length-prefixed extrinsic, failing if it is not a signed `submitData` call
+    pub fn from_bytes(mut bytes: &[u8]) -> eyre::Result<Self> {
+        let body = Vec::<u8>::decode(&mut bytes)?;
+        let extrinsic = SignedExtrinsic::decode(&mut body.as_slice())?;
+        if extrinsic.version != SIGNED_EXTRINSIC_V4 {
+            return Err(eyre::eyre!(
+                "Unsupported extrinsic version {}",
+                extrinsic.version
+            ));
+        }
+        if (extrinsic.call.pallet_index, extrinsic.call.call_index)
+            != (DATA_AVAILABILITY_PALLET, SUBMIT_DATA_CALL)
+        {
+            return Err(eyre::eyre!("Extrinsic is not a submitData call"));
+        }
+        Ok(extrinsic)
+    }
+}
+
+/// What an extrinsic's signature commits to besides its call and extra
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SigningParams {
+    pub spec_version: u32,
+    pub transaction_version: u32,
+    pub genesis_hash: B256,
+}
+
+/// The bytes an extrinsic's signer signs: the call, the extra and the implicit data of the
+/// signed extensions, hashed when too long. Immortal extrinsics check against the genesis hash
+pub fn signing_payload(
+    call: &SubmitDataCall,
+    extra: &SignedExtra,
+    params: &SigningParams,
+) -> Vec<u8> {
+    let mut payload = call.encode();
+    extra.encode_to(&mut payload);
+    (
+        params.spec_version,
+        params.transaction_version,
+        params.genesis_hash.0,
+        params.genesis_hash.0,
+    )
+        .encode_to(&mut payload);
+    if payload.len() > MAX_UNHASHED_PAYLOAD {
+        blake2_256(&payload).to_vec()
+    } else {
+        payload
+    }
+}
+
+pub fn blake2_256(data: &[u8]) -> B256 {
+    B256::from_slice(&Blake2b::<U32>::digest(data))
+}
+
+/// The SS58 address of an sr25519 public key
+pub fn ss58_address(public_key: &[u8; 32]) -> String {
+    let mut bytes = vec![SS58_PREFIX];
+    bytes.extend_from_slice(public_key);
+    let checksum = Blake2b512::new()
+        .chain_update(b"SS58PRE")
+        .chain_update(&bytes)
+        .finalize();
+    bytes.extend_from_slice(&checksum[..2]);
+    bs58::encode(bytes).into_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RuntimeVersion {
+    spec_version: u32,
+    transaction_version: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Header {
+    number: U64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SignedBlock {
+    block: Block,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Block {
+    header: Header,
+    extrinsics: Vec<Bytes>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeeInfo {
+    #[serde_as(as = "DisplayFromStr")]
+    partial_fee: U256,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SystemHealth {
+    is_syncing: bool,
+    peers: u64,
+}
+
+pub struct AvailClient {
+    client: RpcClient<Http<Client>>,
+    keypair: Keypair,
+    app_id: u32,
+    inclusion_timeout: Duration,
+    /// Held from reading the account nonce until the extrinsic using it is in the pool
+    submit_lock: Mutex<()>,
+}
+
+impl AvailClient {
+    /// Creates a client signing with the sr25519 key of the 32-byte `seed`, and submitting
+    /// under application `app_id`
+    pub fn new(
+        rpc_url: Url,
+        seed: B256,
+        app_id: u32,
+        inclusion_timeout: Duration,
+    ) -> eyre::Result<Self> {
+        let keypair = MiniSecretKey::from_bytes(seed.as_slice())
+            .map_err(|e| eyre::eyre!("Invalid Avail seed: {e}"))?
+            .expand_to_keypair(ExpansionMode::Ed25519);
+        Ok(Self {
+            client: RpcClient::new_http(rpc_url),
+            keypair,
+            app_id,
+            inclusion_timeout,
+            submit_lock: Mutex::new(()),
+        })
+    }
+
+    pub fn address(&self) -> String {
+        ss58_address(&self.keypair.public.to_bytes())
+    }
+
+    async fn signing_params(&self) -> eyre::Result<SigningParams> {
+        let genesis_hash: B256 = self.client.request("chain_getBlockHash", (0,)).await?;
+        let runtime: RuntimeVersion = self
+            .client
+            .request_noparams("state_getRuntimeVersion")
+            .await?;
+        if !SUPPORTED_SPEC_VERSIONS.contains(&runtime.spec_version) {
+            return Err(eyre::eyre!(
+                "Unsupported Avail runtime spec version {}, expected one of {SUPPORTED_SPEC_VERSIONS:?}",
+                runtime.spec_version
+            ));
+        }
+        Ok(SigningParams {
+            spec_version: runtime.spec_version,
+            transaction_version: runtime.transaction_version,
+            genesis_hash,
+        })
+    }
+
+    /// Builds and signs an immortal `submitData` extrinsic
+    fn extrinsic(&self, data: Vec<u8>, nonce: u32, params: &SigningParams) -> SignedExtrinsic {
+        let call = SubmitDataCall {
+            pallet_index: DATA_AVAILABILITY_PALLET,
+            call_index: SUBMIT_DATA_CALL,
+            data,
+        };
+        let extra = SignedExtra {
+            era: Era::Immortal,
+            nonce,
+            tip: 0,
+            app_id: self.app_id,
+        };
+        let signature = self
+            .keypair
+            .sign_simple(SIGNING_CONTEXT, &signing_payload(&call, &extra, params));
+        SignedExtrinsic {
+            version: SIGNED_EXTRINSIC_V4,
+            signer: MultiAddress::Id(self.keypair.public.to_bytes()),
+            signature: MultiSignature::Sr25519(signature.to_bytes()),
+            extra,
+            call,
+        }
+    }
+
+    async fn finalized_number(&self) -> eyre::Result<u64> {
+        let hash: B256 = self
+            .client
+            .request_noparams("chain_getFinalizedHead")
+            .await?;
+        let header: Header = self.client.request("chain_getHeader", (hash,)).await?;
+        Ok(header.number.to())
+    }
+
+    /// The block of `hash`, or `None` if the node does not know it
+    async fn block(&self, hash: B256) -> eyre::Result<Option<Block>> {
+        let block: Option<SignedBlock> = self.client.request("chain_getBlock", (hash,)).await?;
+        Ok(block.map(|block| block.block))
+    }
+
+    /// Scans the blocks finalized after `after` until one includes the extrinsic of `tx_hash`,
+    /// returning its hash, number and the index of the extrinsic
+    async fn wait_for_inclusion(
+        &self,
+        tx_hash: B256,
+        after: u64,
+    ) -> eyre::Result<(B256, u64, u32)> {
+        let deadline = Instant::now() + self.inclusion_timeout;
+        let mut next = after + 1;
+        loop {
+            let finalized = self.finalized_number().await?;
+            while next <= finalized {
+                let hash: B256 = self.client.request("chain_getBlockHash", (next,)).await?;
+                let block = self
+                    .block(hash)
+                    .await?
+                    .ok_or_else(|| eyre::eyre!("Finalized block {next} not found"))?;
+                if let Some(index) = block
+                    .extrinsics
+                    .iter()
+                    .position(|extrinsic| blake2_256(extrinsic) == tx_hash)
+                {
+                    return Ok((hash, next, index.try_into()?));
+                }
+                next += 1;
+            }
+            if Instant::now() >= deadline {
+                return Err(eyre::eyre!(
+                    "Extrinsic {tx_hash} not finalized within {:?}",
+                    self.inclusion_timeout
+                ));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// The data of the `submitData` extrinsic of `receipt`, or `None` if the chain certainly
+    /// does not hold it. Fails if the node does not have the block, e.g. once it was pruned
+    async fn submitted_data(&self, receipt: &AvailReceipt) -> eyre::Result<Option<Vec<u8>>> {
+        let hash: Option<B256> = self
+            .client
+            .request("chain_getBlockHash", (receipt.block_number,))
+            .await?;
+        match hash {
+            Some(hash) if hash == receipt.block_hash => {}
+            Some(_) => return Ok(None),
+            None => {
+                return Err(eyre::eyre!(
+                    "Avail node has no block {}",
+                    receipt.block_number
+                ))
+            }
+        }
+        let block = self.block(receipt.block_hash).await?.ok_or_else(|| {
+            eyre::eyre!(
+                "Avail node has no body for block {} {}",
+                receipt.block_number,
+                receipt.block_hash
+            )
+        })?;
+        if block.header.number.to::<u64>() != receipt.block_number {
+            return Err(eyre::eyre!(
+                "Avail node returned block {} for {}",
+                block.header.number,
+                receipt.block_number
+            ));
+        }
+        Ok(block
+            .extrinsics
+            .get(receipt.extrinsic_index as usize)
+            .and_then(|extrinsic| SignedExtrinsic::from_bytes(extrinsic).ok())
+            .map(|extrinsic| extrinsic.call.data))
+    }
+}
+
+impl Submitter for AvailClient {
+    type Receipt = AvailReceipt;
+
+    async fn submit(
+        &self,
+        provided_commitment: &str,
+        blob_data: BlobData,
+    ) -> eyre::Result<Self::Receipt> {
+        let data_hash = keccak256(&blob_data.data);
+        if B256::from_str(provided_commitment).ok() != Some(data_hash) {
+            return Err(eyre::eyre!(
+                "Provided commitment does not match computed commitment {data_hash}"
+            ));
+        }
+
+        let params = self.signing_params().await?;
+        let (tx_hash, after) = {
+            let _guard = self.submit_lock.lock().await;
+            let after = self.finalized_number().await?;
+            let nonce: u32 = self
+                .client
+                .request("system_accountNextIndex", (self.address(),))
+                .await?;
+            let extrinsic = self.extrinsic(blob_data.data, nonce, &params);
+            let tx_hash: B256 = self
+                .client
+                .request(
+                    "author_submitExtrinsic",
+                    (Bytes::from(extrinsic.to_bytes()),),
+                )
+                .await?;
+            (tx_hash, after)
+        };
+        let (block_hash, block_number, extrinsic_index) =
+            self.wait_for_inclusion(tx_hash, after).await?;
+
+        tracing::info!(
+            "[Avail] Submitted data with hash {data_hash} in extrinsic {block_number}-{extrinsic_index}"
+        );
+        Ok(AvailReceipt {
+            block_hash,
+            block_number,
+            extrinsic_index,
+            data_hash,
+        })
+    }
+}
+
+impl Estimator for AvailClient {
+    const NATIVE_ASSET: Asset = Asset::Avail;
+
+    async fn estimate_cost(&self, size: u64) -> eyre::Result<U256> {
+        let params = self.signing_params().await?;
+        let extrinsic = self.extrinsic(vec![0; size.try_into()?], 0, &params);
+        let info: FeeInfo = self
+            .client
+            .request("payment_queryInfo", (Bytes::from(extrinsic.to_bytes()),))
+            .await?;
+        Ok(info.partial_fee)
+    }
+}
+
+impl Verifier for AvailClient {
+    type Receipt = AvailReceipt;
+
+    async fn is_included(&self, receipt: &Self::Receipt) -> eyre::Result<bool> {
+        let finalized = self.finalized_number().await?;
+        if receipt.block_number > finalized {
+            return Err(eyre::eyre!(
+                "Avail node finalized block {finalized}, behind receipt block {}",
+                receipt.block_number
+            ));
+        }
+        Ok(self
+            .submitted_data(receipt)
+            .await?
+            .is_some_and(|data| keccak256(data) == receipt.data_hash))
+    }
+
+    async fn verify_post(&self, receipt: &Self::Receipt, data: &BlobData) -> eyre::Result<()> {
+        if self.submitted_data(receipt).await?.as_ref() != Some(&data.data) {
+            return Err(eyre::eyre!(
+                "Extrinsic {}-{} differs from the posted data",
+                receipt.block_number,
+                receipt.extrinsic_index
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Prover for AvailClient {
+    type Receipt = AvailReceipt;
+
+    async fn inclusion_proof(&self, _receipt: &Self::Receipt) -> eyre::Result<Bytes> {
+        Err(eyre::eyre!("Avail receipts have no on-chain verifier yet"))
+    }
+}
+
+impl Health for AvailClient {
+    async fn health(&self) -> eyre::Result<()> {
+        let health: SystemHealth = self.client.request_noparams("system_health").await?;
+        if health.is_syncing {
+            return Err(eyre::eyre!(
+                "Avail node is syncing with {} peers",
+                health.peers
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as StdMutex};
+
+    use axum::{extract::State, routing::post, Json, Router};
+    use schnorrkel::{PublicKey, Signature};
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    const SPEC_VERSION: u32 = SUPPORTED_SPEC_VERSIONS[0];
+    const TRANSACTION_VERSION: u32 = 1;
+
+    /// A chain answering the JSON-RPC calls of the client, finalizing every extrinsic it is
+    /// sent in a block of its own
+    struct MockChain {
+        genesis_hash: B256,
+        blocks: Vec<(B256, Vec<Bytes>)>,
+        nonce: u32,
+        spec_version: u32,
+        /// Blocks below this number have their body pruned
+        pruned_below: usize,
+    }
+
+    impl MockChain {
+        fn block(&self, hash: &Value) -> Option<(usize, &Vec<Bytes>)> {
+            let hash: B256 = serde_json::from_value(hash.clone()).unwrap();
+            self.blocks
+                .iter()
+                .enumerate()
+                .find(|(_, (block_hash, _))| *block_hash == hash)
+                .map(|(number, (_, extrinsics))| (number, extrinsics))
+        }
+
+        fn body(&self, hash: &Value) -> Option<(usize, &Vec<Bytes>)> {
+            self.block(hash)
+                .filter(|(number, _)| *number >= self.pruned_below)
+        }
+
+        fn submit(&mut self, bytes: &Bytes) -> B256 {
+            let extrinsic = SignedExtrinsic::from_bytes(bytes).unwrap();
+            let MultiAddress::Id(signer) = extrinsic.signer else {
+                panic!("Unexpected signer {:?}", extrinsic.signer);
+            };
+            let MultiSignature::Sr25519(signature) = extrinsic.signature else {
+                panic!("Unexpected signature {:?}", extrinsic.signature);
+            };
+            let params = SigningParams {
+                spec_version: SPEC_VERSION,
+                transaction_version: TRANSACTION_VERSION,
+                genesis_hash: self.genesis_hash,
+            };
+            PublicKey::from_bytes(&signer)
+                .unwrap()
+                .verify_simple(
+                    SIGNING_CONTEXT,
+                    &signing_payload(&extrinsic.call, &extrinsic.extra, &params),
+                    &Signature::from_bytes(&signature).unwrap(),
+                )
+                .unwrap();
+            assert_eq!(extrinsic.extra.nonce, self.nonce);
+            self.nonce += 1;
+
+            let number = self.blocks.len() as u8;
+            self.blocks.push((
+                B256::repeat_byte(number + 1),
+                vec![Bytes::from_static(&[0x04, 0x03]), bytes.clone()],
+            ));
+            blake2_256(bytes)
+        }
+    }
+
+    async fn rpc(
+        State(chain): State<Arc<StdMutex<MockChain>>>,
+        Json(request): Json<Value>,
+    ) -> Json<Value> {
+        let mut chain = chain.lock().unwrap();
+        let params = &request["params"];
+        let result = match request["method"].as_str().unwrap() {
+            "chain_getBlockHash" => json!(chain
+                .blocks
+                .get(params[0].as_u64().unwrap() as usize)
+                .map(|(hash, _)| hash)),
+            "chain_getFinalizedHead" => json!(chain.blocks.last().unwrap().0),
+            "chain_getHeader" => {
+                let (number, _) = chain.block(&params[0]).unwrap();
+                json!({ "number": format!("{number:#x}") })
+            }
+            "chain_getBlock" => match chain.body(&params[0]) {
+                Some((number, extrinsics)) => json!({
+                    "block": {
+                        "header": { "number": format!("{number:#x}") },
+                        "extrinsics": extrinsics,
+                    }
+                }),
+                None => Value::Null,
+            },
+            "state_getRuntimeVersion" => json!({
+                "specVersion": chain.spec_version,
+                "transactionVersion": TRANSACTION_VERSION,
+            }),
+            "system_accountNextIndex" => json!(chain.nonce),
+            "author_submitExtrinsic" => {
+                let bytes: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                json!(chain.submit(&bytes))
+            }
+            "payment_queryInfo" => {
+                let bytes: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                json!({ "partialFee": (bytes.len() * 1000).to_string() })
+            }
+            "system_health" => json!({ "isSyncing": false, "peers": 3, "shouldHavePeers": true }),
+            method => panic!("Unexpected method {method}"),
+        };
+        Json(json!({ "jsonrpc": "2.0", "id": request["id"], "result": result }))
+    }
+
+    async fn mock_client() -> (AvailClient, Arc<StdMutex<MockChain>>) {
+        let genesis_hash = B256::repeat_byte(0xaa);
+        let chain = Arc::new(StdMutex::new(MockChain {
+            genesis_hash,
+            blocks: vec![(genesis_hash, Vec::new())],
+            nonce: 5,
+            spec_version: SPEC_VERSION,
+            pruned_below: 0,
+        }));
+        let app = Router::new()
+            .route("/", post(rpc))
+            .with_state(chain.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        let client =
+            AvailClient::new(url, B256::repeat_byte(1), 7, Duration::from_secs(1)).unwrap();
+        (client, chain)
+    }
+
+    #[test]
+    fn test_ss58_address() {
+        // Alice's well-known development account
+        let public_key: [u8; 32] =
+            hex::decode("d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d")
+                .unwrap()
+                .try_into()
+                .unwrap();
+        assert_eq!(
+            ss58_address(&public_key),
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"
+        );
+    }
+
+    #[test]
+    fn test_extrinsic_encoding() {
+        let extrinsic = SignedExtrinsic {
+            version: SIGNED_EXTRINSIC_V4,
+            signer: MultiAddress::Id([1; 32]),
+            signature: MultiSignature::Sr25519([2; 64]),
+            extra: SignedExtra {
+                era: Era::Mortal(0x0b25),
+                nonce: 70,
+                tip: 0,
+                app_id: 7,
+            },
+            call: SubmitDataCall {
+                pallet_index: DATA_AVAILABILITY_PALLET,
+                call_index: SUBMIT_DATA_CALL,
+                data: b"hello".to_vec(),
+            },
+        };
+        let bytes = extrinsic.to_bytes();
+        // Two-byte compact length of the 113 bytes that follow, then the signed version
+        assert_eq!(bytes[..3], [0xc5, 0x01, 0x84]);
+        assert_eq!(bytes[bytes.len() - 8..], *b"\x1d\x01\x14hello");
+        assert_eq!(SignedExtrinsic::from_bytes(&bytes).unwrap(), extrinsic);
+
+        let mut other_call = extrinsic.clone();
+        other_call.call.pallet_index = 4;
+        assert!(SignedExtrinsic::from_bytes(&other_call.to_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_receipt_context() {
+        let receipt = AvailReceipt {
+            block_hash: B256::repeat_byte(3),
+            block_number: 42,
+            extrinsic_index: 2,
+            data_hash: keccak256(b"hello"),
+        };
+        let decoded =
+            AvailReceipt::from_context(&receipt.data_hash.to_string(), &receipt.context()).unwrap();
+        assert_eq!(decoded, receipt);
+        assert!(AvailReceipt::from_context("0x1234", &receipt.context()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_submit() {
+        let (client, chain) = mock_client().await;
+        let data = BlobData {
+            namespace: None,
+            data: b"hello avail".to_vec(),
+        };
+        let commitment = keccak256(&data.data).to_string();
+
+        assert!(
+            Submitter::submit(&client, &B256::ZERO.to_string(), data.clone())
+                .await
+                .is_err()
+        );
+
+        let receipt = Submitter::submit(&client, &commitment, data.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            receipt,
+            AvailReceipt {
+                block_hash: B256::repeat_byte(2),
+                block_number: 1,
+                extrinsic_index: 1,
+                data_hash: keccak256(&data.data),
+            }
+        );
+        assert!(Verifier::is_included(&client, &receipt).await.unwrap());
+        Verifier::verify_post(&client, &receipt, &data)
+            .await
+            .unwrap();
+
+        // The next submission uses the next nonce
+        let receipt = Submitter::submit(&client, &commitment, data.clone())
+            .await
+            .unwrap();
+        assert_eq!(receipt.block_number, 2);
+
+        let wrong_index = AvailReceipt {
+            extrinsic_index: 0,
+            ..receipt.clone()
+        };
+        assert!(!Verifier::is_included(&client, &wrong_index).await.unwrap());
+        let unknown_block = AvailReceipt {
+            block_hash: B256::repeat_byte(9),
+            ..receipt.clone()
+        };
+        assert!(!Verifier::is_included(&client, &unknown_block)
+            .await
+            .unwrap());
+        let future_block = AvailReceipt {
+            block_number: 10,
+            ..receipt.clone()
+        };
+        assert!(Verifier::is_included(&client, &future_block).await.is_err());
+
+        chain.lock().unwrap().pruned_below = 3;
+        assert!(Verifier::is_included(&client, &receipt).await.is_err());
+        assert!(Verifier::verify_post(&client, &receipt, &data)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cost_and_health() {
+        let (client, chain) = mock_client().await;
+        let small = Estimator::estimate_cost(&client, 10).await.unwrap();
+        let large = Estimator::estimate_cost(&client, 1000).await.unwrap();
+        assert!(small > U256::ZERO);
+        assert!(large > small);
+        Health::health(&client).await.unwrap();
+
+        chain.lock().unwrap().spec_version = SPEC_VERSION + 1;
+        let error = Estimator::estimate_cost(&client, 10).await.unwrap_err();
+        assert!(error.to_string().contains("spec version"));
+    }
+}
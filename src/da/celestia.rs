@@ -1,26 +1,99 @@
+use alloy::{
+    primitives::{Bytes, FixedBytes, B256, U256},
+    sol_types::SolValue,
+};
 use base64::Engine;
 use borsh::{BorshDeserialize, BorshSerialize};
-use celestia_rpc::{BlobClient, Client};
-use celestia_types::{nmt::NS_SIZE, Blob, Commitment, TxConfig};
+use celestia_rpc::{BlobClient, Client, HeaderClient};
+use celestia_tendermint_proto::v0_34::types::RowProof as RawRowProof;
+use celestia_types::{
+    consts::appconsts::{
+        CONTINUATION_SPARSE_SHARE_CONTENT_SIZE, FIRST_SPARSE_SHARE_CONTENT_SIZE, SHARE_SIZE,
+    },
+    nmt::{NamespaceProof, NamespacedHash, NS_SIZE},
+    Blob, Commitment, DataAvailabilityHeader, Share, TxConfig,
+};
+use eyre::OptionExt;
+use serde::{Deserialize, Serialize};
 use url::Url;
 
-use super::{BlobData, Submitter};
+use crate::{
+    contracts::celestia_verifier::{
+        BinaryMerkleProof, Namespace as VerifierNamespace, NamespaceMerkleMultiproof,
+        NamespaceNode, SharesProof,
+    },
+    price::Asset,
+};
 
+use super::{BlobData, Estimator, Health, Prover, Submitter, Verifier};
+
+/// Default minimum gas price of celestia-node, in utia
+pub const DEFAULT_GAS_PRICE: f64 = 0.002;
+/// Gas consumed per byte of blob shares
+const GAS_PER_BLOB_BYTE: u64 = 8;
+/// Gas consumed per byte of transaction
+const TX_SIZE_COST_PER_BYTE: u64 = 10;
+/// Approximate size in bytes of the `MsgPayForBlobs` info of a single blob
+const BYTES_PER_BLOB_INFO: u64 = 70;
+/// Fixed gas cost of a `MsgPayForBlobs` transaction
+const PFB_GAS_FIXED_COST: u64 = 75_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CelestiaReceipt {
     pub height: u64,
     pub commitment: Commitment,
     pub namespace: celestia_types::nmt::Namespace,
 }
 
+impl CelestiaReceipt {
+    /// ABI-encoded `(namespace, height)` context passed to `Kuda::submitReceipt`
+    pub fn context(&self) -> Bytes {
+        Bytes::copy_from_slice(&(self.namespace.0, self.height).abi_encode())
+    }
+
+    /// Rebuilds a receipt from the base64 `commitment` and `context` of a `Kuda::submitReceipt`
+    /// call
+    pub fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self> {
+        let (namespace, height) = <(FixedBytes<NS_SIZE>, u64)>::abi_decode(context, true)?;
+        let commitment: [u8; 32] = base64::engine::general_purpose::STANDARD
+            .decode(commitment)?
+            .try_into()
+            .map_err(|_| eyre::eyre!("Celestia commitment must be 32 bytes"))?;
+        Ok(CelestiaReceipt {
+            height,
+            commitment: Commitment(commitment),
+            namespace: celestia_types::nmt::Namespace::from_raw(namespace.as_slice())?,
+        })
+    }
+}
+
 pub struct CelestiaClient {
     client: Client,
+    gas_price: Option<f64>,
 }
 
 impl CelestiaClient {
-    pub async fn new(url: &Url, token: Option<&str>) -> eyre::Result<Self> {
+    /// Creates a client submitting at `gas_price` utia, or at the node's minimum gas price if `None`
+    pub async fn new(url: &Url, token: Option<&str>, gas_price: Option<f64>) -> eyre::Result<Self> {
         let client = Client::new(url.as_str(), token).await?;
-        Ok(Self { client })
+        Ok(Self { client, gas_price })
+    }
+}
+
+/// Number of sparse shares needed to hold a blob of `size` bytes
+pub fn shares_for_size(size: u64) -> u64 {
+    let first_share_size = FIRST_SPARSE_SHARE_CONTENT_SIZE as u64;
+    if size <= first_share_size {
+        return 1;
     }
+    1 + (size - first_share_size).div_ceil(CONTINUATION_SPARSE_SHARE_CONTENT_SIZE as u64)
+}
+
+/// Gas needed to pay for a single blob of `size` bytes
+pub fn estimate_gas(size: u64) -> u64 {
+    shares_for_size(size) * SHARE_SIZE as u64 * GAS_PER_BLOB_BYTE
+        + TX_SIZE_COST_PER_BYTE * BYTES_PER_BLOB_INFO
+        + PFB_GAS_FIXED_COST
 }
 
 impl Submitter for CelestiaClient {
@@ -43,10 +116,11 @@ impl Submitter for CelestiaClient {
         }
 
         // submit it
-        let height = self
-            .client
-            .blob_submit(&[blob], TxConfig::default())
-            .await?;
+        let tx_config = TxConfig {
+            gas_price: self.gas_price,
+            ..Default::default()
+        };
+        let height = self.client.blob_submit(&[blob], tx_config).await?;
 
         tracing::info!(
             "[Celestia] Submitted blob with commitment {computed_commitment} at height {height} "
@@ -61,7 +135,215 @@ impl Submitter for CelestiaClient {
     }
 }
 
-#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+impl Estimator for CelestiaClient {
+    const NATIVE_ASSET: Asset = Asset::Tia;
+
+    async fn estimate_cost(&self, size: u64) -> eyre::Result<U256> {
+        let gas_price = self.gas_price.unwrap_or(DEFAULT_GAS_PRICE);
+        let cost = (estimate_gas(size) as f64 * gas_price).ceil();
+        Ok(U256::try_from(cost)?)
+    }
+}
+
+impl CelestiaClient {
+    /// Fetches the blob of `receipt` back and proves that its shares are in the data root of
+    /// the block at its height, checking the proof before returning it
+    pub async fn shares_proof(&self, receipt: &CelestiaReceipt) -> eyre::Result<SharesProof> {
+        let blob = self
+            .client
+            .blob_get(receipt.height, receipt.namespace, receipt.commitment)
+            .await?;
+        if blob.commitment != receipt.commitment {
+            return Err(eyre::eyre!("Blob fetched back has another commitment"));
+        }
+        let index = blob
+            .index
+            .ok_or_eyre("Blob fetched back without its share index")?;
+        let shares = blob.to_shares()?;
+        let proofs = self
+            .client
+            .blob_get_proof(receipt.height, receipt.namespace, receipt.commitment)
+            .await?;
+        let header = self.client.header_get_by_height(receipt.height).await?;
+
+        let proof = build_shares_proof(
+            receipt.height,
+            receipt.namespace,
+            index,
+            &shares,
+            proofs,
+            &header.dah,
+        )?;
+        tracing::info!(
+            "[Celestia] Proved {} shares of blob at height {} over {} rows",
+            shares.len(),
+            receipt.height,
+            proof.rowRoots.len()
+        );
+        Ok(proof)
+    }
+}
+
+impl Prover for CelestiaClient {
+    type Receipt = CelestiaReceipt;
+
+    async fn inclusion_proof(&self, receipt: &Self::Receipt) -> eyre::Result<Bytes> {
+        Ok(Bytes::from(self.shares_proof(receipt).await?.abi_encode()))
+    }
+}
+
+impl Verifier for CelestiaClient {
+    type Receipt = CelestiaReceipt;
+
+    async fn is_included(&self, receipt: &Self::Receipt) -> eyre::Result<bool> {
+        let head = self.client.header_local_head().await?.height().value();
+        if receipt.height > head {
+            return Err(eyre::eyre!(
+                "Celestia node is at height {head}, behind receipt height {}",
+                receipt.height
+            ));
+        }
+
+        match self
+            .client
+            .blob_get(receipt.height, receipt.namespace, receipt.commitment)
+            .await
+        {
+            Ok(blob) => Ok(blob.commitment == receipt.commitment),
+            Err(e) if e.to_string().contains("blob: not found") => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn verify_post(&self, receipt: &Self::Receipt, data: &BlobData) -> eyre::Result<()> {
+        let blob = self
+            .client
+            .blob_get(receipt.height, receipt.namespace, receipt.commitment)
+            .await?;
+        if blob.commitment != receipt.commitment || blob.data != data.data {
+            return Err(eyre::eyre!(
+                "Blob read back at height {} differs from the posted data",
+                receipt.height
+            ));
+        }
+        tracing::info!(
+            "[Celestia] Read back blob at height {} in namespace {}",
+            receipt.height,
+            hex::encode(receipt.namespace.0)
+        );
+        Ok(())
+    }
+}
+
+impl Health for CelestiaClient {
+    async fn health(&self) -> eyre::Result<()> {
+        let state = self.client.header_sync_state().await?;
+        if state.height < state.to_height {
+            return Err(eyre::eyre!(
+                "Celestia node is syncing, at height {} of {}",
+                state.height,
+                state.to_height
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Checks the namespace proof of every row the blob spans against the row roots, and the row
+/// roots against the data root, then lays the proof out for the `celestiaVerifier`.
+/// `index` is the position of the first share of the blob in the extended data square
+pub fn build_shares_proof(
+    height: u64,
+    namespace: celestia_types::nmt::Namespace,
+    index: u64,
+    shares: &[Share],
+    proofs: Vec<NamespaceProof>,
+    dah: &DataAvailabilityHeader,
+) -> eyre::Result<SharesProof> {
+    if proofs.is_empty() {
+        return Err(eyre::eyre!("No namespace proofs for the blob"));
+    }
+    let start_row = u16::try_from(index / u64::from(dah.square_width()))?;
+    let end_row = start_row + u16::try_from(proofs.len() - 1)?;
+    let row_proof = dah.row_proof(start_row..=end_row)?;
+    let data_root = dah.hash();
+    row_proof.verify(data_root)?;
+
+    let mut remaining = shares;
+    let mut share_proofs = Vec::with_capacity(proofs.len());
+    for (proof, row_root) in proofs.into_iter().zip(row_proof.row_roots()) {
+        let proof = proof.into_inner();
+        let row_shares = proof.end_idx().saturating_sub(proof.start_idx()) as usize;
+        if row_shares > remaining.len() {
+            return Err(eyre::eyre!(
+                "Namespace proofs cover more shares than the blob"
+            ));
+        }
+        let (row, rest) = remaining.split_at(row_shares);
+        proof
+            .verify_range(row_root, row, *namespace)
+            .map_err(|e| eyre::eyre!("Invalid namespace proof: {e:?}"))?;
+        share_proofs.push(NamespaceMerkleMultiproof {
+            beginKey: U256::from(proof.start_idx()),
+            endKey: U256::from(proof.end_idx()),
+            sideNodes: proof.siblings().iter().map(namespace_node).collect(),
+        });
+        remaining = rest;
+    }
+    if !remaining.is_empty() {
+        return Err(eyre::eyre!(
+            "Namespace proofs do not cover {} shares of the blob",
+            remaining.len()
+        ));
+    }
+
+    let row_roots = row_proof.row_roots().iter().map(namespace_node).collect();
+    let row_proofs = RawRowProof::from(row_proof)
+        .proofs
+        .into_iter()
+        .map(|proof| -> eyre::Result<BinaryMerkleProof> {
+            Ok(BinaryMerkleProof {
+                sideNodes: proof
+                    .aunts
+                    .iter()
+                    .map(|aunt| B256::try_from(aunt.as_slice()))
+                    .collect::<Result<_, _>>()?,
+                key: U256::from(proof.index),
+                numLeaves: U256::from(proof.total),
+            })
+        })
+        .collect::<eyre::Result<_>>()?;
+
+    Ok(SharesProof {
+        height,
+        dataRoot: B256::try_from(data_root.as_bytes())?,
+        data: shares
+            .iter()
+            .map(|share| Bytes::copy_from_slice(share.as_ref()))
+            .collect(),
+        shareProofs: share_proofs,
+        namespace: namespace_id(&namespace.0),
+        rowRoots: row_roots,
+        rowProofs: row_proofs,
+    })
+}
+
+fn namespace_id(namespace: &[u8; NS_SIZE]) -> VerifierNamespace {
+    VerifierNamespace {
+        version: FixedBytes([namespace[0]]),
+        id: FixedBytes::from_slice(&namespace[1..]),
+    }
+}
+
+fn namespace_node(hash: &NamespacedHash) -> NamespaceNode {
+    NamespaceNode {
+        min: namespace_id(&hash.min_namespace().0),
+        max: namespace_id(&hash.max_namespace().0),
+        digest: B256::from(hash.hash()),
+    }
+}
+
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub struct Namespace(pub [u8; NS_SIZE]);
 
 impl TryFrom<BlobData> for Blob {
@@ -83,6 +365,9 @@ impl TryFrom<BlobData> for Blob {
 mod tests {
     use std::str::FromStr;
 
+    use celestia_types::ExtendedDataSquare;
+    use nmt_rs::nmt_proof::NamespaceProof as NmtNamespaceProof;
+
     use super::*;
 
     #[test]
@@ -98,4 +383,85 @@ mod tests {
         let decoded = BlobData::from_str(&encoded).unwrap();
         assert_eq!(blob_data, decoded);
     }
+
+    #[test]
+    fn test_build_shares_proof() {
+        let namespace = celestia_types::nmt::Namespace::new_v0(&[1, 2, 3]).unwrap();
+        let blob = Blob::new(namespace, vec![7u8; 1000]).unwrap();
+        let shares = blob.to_shares().unwrap();
+        assert_eq!(shares.len(), 3);
+
+        // A 2x2 square holding the blob in its first two rows, followed by tail padding
+        let mut ods: Vec<Vec<u8>> = shares.iter().map(|share| share.as_ref().to_vec()).collect();
+        ods.push(
+            [
+                celestia_types::nmt::Namespace::TAIL_PADDING.as_bytes(),
+                &[1],
+                &[0; SHARE_SIZE - NS_SIZE - 1],
+            ]
+            .concat(),
+        );
+        let eds = ExtendedDataSquare::from_ods(ods).unwrap();
+        let dah = DataAvailabilityHeader::from_eds(&eds);
+
+        let proofs: Vec<NamespaceProof> = [(0, 0..2), (1, 0..1)]
+            .into_iter()
+            .map(|(row, range)| {
+                let proof = eds.row_nmt(row).unwrap().build_range_proof(range);
+                NmtNamespaceProof::PresenceProof {
+                    proof,
+                    ignore_max_ns: true,
+                }
+                .into()
+            })
+            .collect();
+
+        let shares_proof =
+            build_shares_proof(42, namespace, 0, &shares, proofs.clone(), &dah).unwrap();
+        assert_eq!(shares_proof.height, 42);
+        assert_eq!(shares_proof.dataRoot.as_slice(), dah.hash().as_bytes());
+        assert_eq!(shares_proof.data.len(), 3);
+        assert_eq!(shares_proof.shareProofs.len(), 2);
+        assert_eq!(shares_proof.rowRoots.len(), 2);
+        assert_eq!(shares_proof.rowProofs.len(), 2);
+        let encoded = shares_proof.abi_encode();
+        assert_eq!(
+            SharesProof::abi_decode(&encoded, true).unwrap(),
+            shares_proof
+        );
+
+        // Proofs starting at another row or over other shares don't verify
+        assert!(build_shares_proof(42, namespace, 4, &shares, proofs.clone(), &dah).is_err());
+        let mut tampered = shares.clone();
+        tampered.swap(0, 1);
+        assert!(build_shares_proof(42, namespace, 0, &tampered, proofs, &dah).is_err());
+    }
+
+    #[test]
+    fn test_receipt_context() {
+        let commitment = Commitment([3; 32]);
+        let receipt = CelestiaReceipt {
+            height: 100,
+            commitment,
+            namespace: celestia_types::nmt::Namespace::new_v0(&[1, 2, 3]).unwrap(),
+        };
+        let decoded = CelestiaReceipt::from_context(
+            &base64::engine::general_purpose::STANDARD.encode(commitment.0),
+            &receipt.context(),
+        )
+        .unwrap();
+        assert_eq!(decoded, receipt);
+
+        assert!(CelestiaReceipt::from_context("AAAA", &receipt.context()).is_err());
+    }
+
+    #[test]
+    fn test_shares_for_size() {
+        let namespace = celestia_types::nmt::Namespace::new_v0(&[1]).unwrap();
+        for size in [0, 1, 478, 479, 960, 961, 10_000] {
+            let blob = Blob::new(namespace, vec![0u8; size]).unwrap();
+            let shares = blob.to_shares().unwrap().len() as u64;
+            assert_eq!(shares_for_size(size as u64), shares.max(1));
+        }
+    }
 }
@@ -0,0 +1,707 @@
+use std::{str::FromStr, time::Duration};
+
+use alloy::{
+    primitives::{keccak256, Bytes, B256, U256},
+    sol_types::SolValue,
+};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tonic::{
+    codec::ProstCodec,
+    codegen::http::uri::PathAndQuery,
+    transport::{Channel, ClientTlsConfig, Endpoint},
+    Code, Status,
+};
+use url::Url;
+
+use crate::price::Asset;
+
+use super::{BlobData, Estimator, Health, Prover, Submitter, Verifier};
+
+const DISPERSE_BLOB: &str = "/disperser.Disperser/DisperseBlob";
+const GET_BLOB_STATUS: &str = "/disperser.Disperser/GetBlobStatus";
+const RETRIEVE_BLOB: &str = "/disperser.Disperser/RetrieveBlob";
+
+/// Size of a symbol, the field element blobs are dispersed and paid in
+const SYMBOL_SIZE: usize = 32;
+/// Payload bytes carried by a symbol, whose first byte must stay zero
+const SYMBOL_DATA_SIZE: usize = SYMBOL_SIZE - 1;
+/// Version of the payload header, the first symbol of every blob
+const PAYLOAD_VERSION: u8 = 0;
+
+/// Messages of the disperser API (`disperser.proto` in the EigenDA repository), holding only
+/// the fields we use
+pub mod proto {
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DisperseBlobRequest {
+        #[prost(bytes = "vec", tag = "1")]
+        pub data: Vec<u8>,
+        #[prost(uint32, repeated, tag = "2")]
+        pub custom_quorum_numbers: Vec<u32>,
+        #[prost(string, tag = "3")]
+        pub account_id: String,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DisperseBlobReply {
+        #[prost(enumeration = "BlobStatus", tag = "1")]
+        pub result: i32,
+        #[prost(bytes = "vec", tag = "2")]
+        pub request_id: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BlobStatusRequest {
+        #[prost(bytes = "vec", tag = "1")]
+        pub request_id: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BlobStatusReply {
+        #[prost(enumeration = "BlobStatus", tag = "1")]
+        pub status: i32,
+        #[prost(message, optional, tag = "2")]
+        pub info: Option<BlobInfo>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BlobInfo {
+        #[prost(message, optional, tag = "2")]
+        pub blob_verification_proof: Option<BlobVerificationProof>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BlobVerificationProof {
+        #[prost(uint32, tag = "1")]
+        pub batch_id: u32,
+        #[prost(uint32, tag = "2")]
+        pub blob_index: u32,
+        #[prost(message, optional, tag = "3")]
+        pub batch_metadata: Option<BatchMetadata>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct BatchMetadata {
+        #[prost(uint32, tag = "4")]
+        pub confirmation_block_number: u32,
+        #[prost(bytes = "vec", tag = "5")]
+        pub batch_header_hash: Vec<u8>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RetrieveBlobRequest {
+        #[prost(bytes = "vec", tag = "1")]
+        pub batch_header_hash: Vec<u8>,
+        #[prost(uint32, tag = "2")]
+        pub blob_index: u32,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct RetrieveBlobReply {
+        #[prost(bytes = "vec", tag = "1")]
+        pub data: Vec<u8>,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+    #[repr(i32)]
+    pub enum BlobStatus {
+        Unknown = 0,
+        Processing = 1,
+        Confirmed = 2,
+        Failed = 3,
+        Finalized = 4,
+        InsufficientSignatures = 5,
+        Dispersing = 6,
+    }
+}
+
+use proto::BlobStatus;
+
+/// Where a blob was confirmed: its batch and its index in the batch, and the keccak hash of
+/// its data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EigenDaReceipt {
+    pub batch_header_hash: B256,
+    pub blob_index: u32,
+    pub data_hash: B256,
+}
+
+impl EigenDaReceipt {
+    /// ABI-encoded `(batchHeaderHash, blobIndex)` context passed to `Kuda::submitReceipt`
+    pub fn context(&self) -> Bytes {
+        Bytes::copy_from_slice(&(self.batch_header_hash, self.blob_index).abi_encode())
+    }
+
+    /// Rebuilds a receipt from the hex data hash `commitment` and `context` of a
+    /// `Kuda::submitReceipt` call
+    pub fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self> {
+        let (batch_header_hash, blob_index) = <(B256, u32)>::abi_decode(context, true)?;
+        Ok(EigenDaReceipt {
+            batch_header_hash,
+            blob_index,
+            data_hash: B256::from_str(commitment)?,
+        })
+    }
+}
+
+/// Codes `data` as a blob of valid field elements: a header symbol holding
+/// [`PAYLOAD_VERSION`] and the data length, followed by the data, 31 bytes per symbol
+pub fn encode_payload(data: &[u8]) -> eyre::Result<Vec<u8>> {
+    let mut blob = vec![0; SYMBOL_SIZE];
+    blob[1] = PAYLOAD_VERSION;
+    blob[2..6].copy_from_slice(&u32::try_from(data.len())?.to_be_bytes());
+    for chunk in data.chunks(SYMBOL_DATA_SIZE) {
+        blob.push(0);
+        blob.extend_from_slice(chunk);
+    }
+    Ok(blob)
+}
+
+/// Decodes a blob coded by [`encode_payload`], ignoring the padding the disperser may append
+pub fn decode_payload(blob: &[u8]) -> eyre::Result<Vec<u8>> {
+    if blob.len() < SYMBOL_SIZE {
+        return Err(eyre::eyre!("Blob of {} bytes has no header", blob.len()));
+    }
+    let (header, body) = blob.split_at(SYMBOL_SIZE);
+    if header[0] != 0 || header[1] != PAYLOAD_VERSION {
+        return Err(eyre::eyre!(
+            "Unsupported blob header {}",
+            hex::encode(header)
+        ));
+    }
+    let length = u32::from_be_bytes(header[2..6].try_into()?) as usize;
+    let mut data = body
+        .chunks(SYMBOL_SIZE)
+        .flat_map(|symbol| symbol.iter().skip(1))
+        .copied()
+        .collect::<Vec<_>>();
+    if data.len() < length {
+        return Err(eyre::eyre!(
+            "Blob holds {} bytes, header says {length}",
+            data.len()
+        ));
+    }
+    data.truncate(length);
+    Ok(data)
+}
+
+pub struct EigenDaClient {
+    endpoint: Endpoint,
+    channel: Channel,
+    poll_interval: Duration,
+    confirmation_timeout: Duration,
+    /// Wei the operator accounts for every symbol it disperses
+    price_per_symbol: U256,
+}
+
+impl EigenDaClient {
+    /// Creates a client dispersing through the disperser at `url`, using TLS for `https` URLs
+    pub fn new(
+        url: &Url,
+        poll_interval: Duration,
+        confirmation_timeout: Duration,
+        price_per_symbol: U256,
+    ) -> eyre::Result<Self> {
+        let mut endpoint = Endpoint::from_shared(url.to_string())?;
+        if url.scheme() == "https" {
+            endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+        }
+        Ok(Self {
+            channel: endpoint.connect_lazy(),
+            endpoint,
+            poll_interval,
+            confirmation_timeout,
+            price_per_symbol,
+        })
+    }
+
+    async fn unary<Req, Resp>(&self, path: &'static str, request: Req) -> Result<Resp, Status>
+    where
+        Req: prost::Message + 'static,
+        Resp: prost::Message + Default + 'static,
+    {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready()
+            .await
+            .map_err(|e| Status::unavailable(format!("Disperser not ready: {e}")))?;
+        let response = grpc
+            .unary(
+                tonic::Request::new(request),
+                PathAndQuery::from_static(path),
+                ProstCodec::default(),
+            )
+            .await?;
+        Ok(response.into_inner())
+    }
+
+    /// Polls the status of a dispersal until its blob is confirmed or finalized. The disperser
+    /// being unavailable or slow to answer is retried until the deadline, since the blob is
+    /// dispersed all the same
+    async fn wait_for_confirmation(
+        &self,
+        request_id: Vec<u8>,
+    ) -> eyre::Result<proto::BlobVerificationProof> {
+        let deadline = Instant::now() + self.confirmation_timeout;
+        loop {
+            let reply = self
+                .unary::<_, proto::BlobStatusReply>(
+                    GET_BLOB_STATUS,
+                    proto::BlobStatusRequest {
+                        request_id: request_id.clone(),
+                    },
+                )
+                .await;
+            match reply {
+                Ok(reply) => match BlobStatus::try_from(reply.status)? {
+                    BlobStatus::Confirmed | BlobStatus::Finalized => {
+                        return reply
+                            .info
+                            .and_then(|info| info.blob_verification_proof)
+                            .ok_or_else(|| {
+                                eyre::eyre!("Confirmed blob has no verification proof")
+                            });
+                    }
+                    status @ (BlobStatus::Failed | BlobStatus::InsufficientSignatures) => {
+                        return Err(eyre::eyre!("Dispersal failed with status {status:?}"));
+                    }
+                    BlobStatus::Unknown | BlobStatus::Processing | BlobStatus::Dispersing => {}
+                },
+                Err(status)
+                    if matches!(status.code(), Code::Unavailable | Code::DeadlineExceeded) =>
+                {
+                    tracing::warn!(
+                        "[EigenDA] Could not poll status of blob {}: {status}",
+                        hex::encode(&request_id)
+                    );
+                }
+                Err(status) => return Err(status.into()),
+            }
+            if Instant::now() >= deadline {
+                return Err(eyre::eyre!(
+                    "Blob {} not confirmed within {:?}",
+                    hex::encode(&request_id),
+                    self.confirmation_timeout
+                ));
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// The data of the blob of `receipt`. Fails if the disperser does not know it, as it may
+    /// not have caught up with the batch or may have pruned it, which doesn't make the blob
+    /// unavailable
+    async fn retrieve(&self, receipt: &EigenDaReceipt) -> eyre::Result<Vec<u8>> {
+        let request = proto::RetrieveBlobRequest {
+            batch_header_hash: receipt.batch_header_hash.to_vec(),
+            blob_index: receipt.blob_index,
+        };
+        let reply: proto::RetrieveBlobReply = self.unary(RETRIEVE_BLOB, request).await?;
+        decode_payload(&reply.data)
+    }
+}
+
+impl Submitter for EigenDaClient {
+    type Receipt = EigenDaReceipt;
+
+    async fn submit(
+        &self,
+        provided_commitment: &str,
+        blob_data: BlobData,
+    ) -> eyre::Result<Self::Receipt> {
+        let data_hash = keccak256(&blob_data.data);
+        if B256::from_str(provided_commitment).ok() != Some(data_hash) {
+            return Err(eyre::eyre!(
+                "Provided commitment does not match computed commitment {data_hash}"
+            ));
+        }
+
+        let reply: proto::DisperseBlobReply = self
+            .unary(
+                DISPERSE_BLOB,
+                proto::DisperseBlobRequest {
+                    data: encode_payload(&blob_data.data)?,
+                    custom_quorum_numbers: Vec::new(),
+                    account_id: String::new(),
+                },
+            )
+            .await?;
+        let status = BlobStatus::try_from(reply.result)?;
+        if matches!(
+            status,
+            BlobStatus::Failed | BlobStatus::InsufficientSignatures
+        ) {
+            return Err(eyre::eyre!("Dispersal failed with status {status:?}"));
+        }
+
+        let proof = self.wait_for_confirmation(reply.request_id).await?;
+        let batch_header_hash = proof
+            .batch_metadata
+            .map(|metadata| metadata.batch_header_hash)
+            .ok_or_else(|| eyre::eyre!("Confirmed blob has no batch metadata"))?;
+        let receipt = EigenDaReceipt {
+            batch_header_hash: B256::try_from(batch_header_hash.as_slice())?,
+            blob_index: proof.blob_index,
+            data_hash,
+        };
+
+        tracing::info!(
+            "[EigenDA] Dispersed data with hash {data_hash} as blob {} of batch {}",
+            receipt.blob_index,
+            receipt.batch_header_hash
+        );
+        Ok(receipt)
+    }
+}
+
+impl Estimator for EigenDaClient {
+    const NATIVE_ASSET: Asset = Asset::Eth;
+
+    async fn estimate_cost(&self, size: u64) -> eyre::Result<U256> {
+        let symbols = 1 + size.div_ceil(SYMBOL_DATA_SIZE as u64);
+        Ok(self.price_per_symbol * U256::from(symbols))
+    }
+}
+
+impl Verifier for EigenDaClient {
+    type Receipt = EigenDaReceipt;
+
+    async fn is_included(&self, receipt: &Self::Receipt) -> eyre::Result<bool> {
+        Ok(keccak256(self.retrieve(receipt).await?) == receipt.data_hash)
+    }
+
+    async fn verify_post(&self, receipt: &Self::Receipt, data: &BlobData) -> eyre::Result<()> {
+        if self.retrieve(receipt).await? != data.data {
+            return Err(eyre::eyre!(
+                "Blob {} of batch {} differs from the posted data",
+                receipt.blob_index,
+                receipt.batch_header_hash
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Prover for EigenDaClient {
+    type Receipt = EigenDaReceipt;
+
+    async fn inclusion_proof(&self, _receipt: &Self::Receipt) -> eyre::Result<Bytes> {
+        Err(eyre::eyre!(
+            "EigenDA receipts have no on-chain verifier yet"
+        ))
+    }
+}
+
+impl Health for EigenDaClient {
+    async fn health(&self) -> eyre::Result<()> {
+        self.endpoint.connect().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        future::{ready, Ready},
+        sync::{Arc, Mutex},
+        task::{Context, Poll},
+    };
+
+    use futures_util::future::BoxFuture;
+    use tonic::{
+        body::BoxBody,
+        codegen::http::{Request, Response},
+        server::{Grpc, NamedService, UnaryService},
+        transport::Server,
+    };
+    use tower::Service;
+
+    use super::*;
+
+    /// A unary gRPC method answered by a closure
+    struct Unary<F>(F);
+
+    impl<Req, Resp, F> UnaryService<Req> for Unary<F>
+    where
+        F: FnMut(Req) -> Result<Resp, Status>,
+    {
+        type Response = Resp;
+        type Future = Ready<Result<tonic::Response<Resp>, Status>>;
+
+        fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+            ready((self.0)(request.into_inner()).map(tonic::Response::new))
+        }
+    }
+
+    struct FakeBlob {
+        data: Vec<u8>,
+        /// How many times the status of the blob was polled
+        polls: u32,
+    }
+
+    /// A disperser confirming every blob on the second status poll, in a batch of its own.
+    /// Blobs ending in 0xff fail, and the first poll of blobs ending in 0xfe is unavailable
+    #[derive(Clone, Default)]
+    struct FakeDisperser {
+        blobs: Arc<Mutex<Vec<FakeBlob>>>,
+    }
+
+    impl FakeDisperser {
+        fn batch_header_hash(index: usize) -> Vec<u8> {
+            B256::repeat_byte(index as u8 + 1).to_vec()
+        }
+
+        fn disperse(
+            &self,
+            request: proto::DisperseBlobRequest,
+        ) -> Result<proto::DisperseBlobReply, Status> {
+            if request
+                .data
+                .chunks(SYMBOL_SIZE)
+                .any(|symbol| symbol[0] != 0)
+            {
+                return Err(Status::invalid_argument(
+                    "Blob is not made of field elements",
+                ));
+            }
+            let mut blobs = self.blobs.lock().unwrap();
+            blobs.push(FakeBlob {
+                data: request.data,
+                polls: 0,
+            });
+            Ok(proto::DisperseBlobReply {
+                result: BlobStatus::Processing.into(),
+                request_id: vec![blobs.len() as u8 - 1],
+            })
+        }
+
+        fn status(
+            &self,
+            request: proto::BlobStatusRequest,
+        ) -> Result<proto::BlobStatusReply, Status> {
+            let index = request.request_id[0] as usize;
+            let mut blobs = self.blobs.lock().unwrap();
+            let blob = blobs
+                .get_mut(index)
+                .ok_or_else(|| Status::not_found("Unknown request"))?;
+            blob.polls += 1;
+            let status = match (blob.data.as_slice(), blob.polls) {
+                ([.., 0xff], _) => BlobStatus::Failed,
+                ([.., 0xfe], 1) => return Err(Status::unavailable("Disperser overloaded")),
+                (_, 1) => BlobStatus::Processing,
+                _ => BlobStatus::Confirmed,
+            };
+            Ok(proto::BlobStatusReply {
+                status: status.into(),
+                info: (status == BlobStatus::Confirmed).then(|| proto::BlobInfo {
+                    blob_verification_proof: Some(proto::BlobVerificationProof {
+                        batch_id: index as u32,
+                        blob_index: 0,
+                        batch_metadata: Some(proto::BatchMetadata {
+                            confirmation_block_number: 100,
+                            batch_header_hash: Self::batch_header_hash(index),
+                        }),
+                    }),
+                }),
+            })
+        }
+
+        fn retrieve(
+            &self,
+            request: proto::RetrieveBlobRequest,
+        ) -> Result<proto::RetrieveBlobReply, Status> {
+            let blobs = self.blobs.lock().unwrap();
+            let blob = (0..blobs.len())
+                .find(|index| Self::batch_header_hash(*index) == request.batch_header_hash)
+                .filter(|_| request.blob_index == 0)
+                .map(|index| &blobs[index])
+                .ok_or_else(|| Status::not_found("Unknown blob"))?;
+            // The disperser pads blobs to a power of two symbols
+            let mut data = blob.data.clone();
+            data.resize(data.len().next_power_of_two(), 0);
+            Ok(proto::RetrieveBlobReply { data })
+        }
+    }
+
+    impl NamedService for FakeDisperser {
+        const NAME: &'static str = "disperser.Disperser";
+    }
+
+    impl Service<Request<BoxBody>> for FakeDisperser {
+        type Response = Response<BoxBody>;
+        type Error = Infallible;
+        type Future = BoxFuture<'static, Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<BoxBody>) -> Self::Future {
+            let disperser = self.clone();
+            Box::pin(async move {
+                Ok(match request.uri().path() {
+                    DISPERSE_BLOB => {
+                        Grpc::new(ProstCodec::default())
+                            .unary(
+                                Unary(|r: proto::DisperseBlobRequest| disperser.disperse(r)),
+                                request,
+                            )
+                            .await
+                    }
+                    GET_BLOB_STATUS => {
+                        Grpc::new(ProstCodec::default())
+                            .unary(
+                                Unary(|r: proto::BlobStatusRequest| disperser.status(r)),
+                                request,
+                            )
+                            .await
+                    }
+                    RETRIEVE_BLOB => {
+                        Grpc::new(ProstCodec::default())
+                            .unary(
+                                Unary(|r: proto::RetrieveBlobRequest| disperser.retrieve(r)),
+                                request,
+                            )
+                            .await
+                    }
+                    _ => Status::unimplemented("Unknown method").into_http(),
+                })
+            })
+        }
+    }
+
+    async fn fake_client() -> EigenDaClient {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(
+            Server::builder()
+                .add_service(FakeDisperser::default())
+                .serve_with_incoming(tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        EigenDaClient::new(
+            &url,
+            Duration::from_millis(10),
+            Duration::from_secs(5),
+            U256::from(10),
+        )
+        .unwrap()
+    }
+
+    fn blob_data(data: &[u8]) -> BlobData {
+        BlobData {
+            namespace: None,
+            data: data.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_payload_coding() {
+        for size in [0, 1, 30, 31, 32, 62, 1000] {
+            let data = (0..size).map(|i| i as u8).collect::<Vec<_>>();
+            let mut blob = encode_payload(&data).unwrap();
+            assert!(blob.chunks(SYMBOL_SIZE).all(|symbol| symbol[0] == 0));
+            assert_eq!(decode_payload(&blob).unwrap(), data);
+            blob.resize(blob.len().next_power_of_two(), 0);
+            assert_eq!(decode_payload(&blob).unwrap(), data);
+        }
+
+        let blob = encode_payload(b"hello").unwrap();
+        assert!(decode_payload(&blob[..SYMBOL_SIZE + 2]).is_err());
+        assert!(decode_payload(&blob[..10]).is_err());
+    }
+
+    #[test]
+    fn test_receipt_context() {
+        let receipt = EigenDaReceipt {
+            batch_header_hash: B256::repeat_byte(3),
+            blob_index: 12,
+            data_hash: keccak256(b"hello"),
+        };
+        let (batch_header_hash, blob_index) =
+            <(B256, u32)>::abi_decode(&receipt.context(), true).unwrap();
+        assert_eq!(
+            (batch_header_hash, blob_index),
+            (receipt.batch_header_hash, 12)
+        );
+        let decoded =
+            EigenDaReceipt::from_context(&receipt.data_hash.to_string(), &receipt.context())
+                .unwrap();
+        assert_eq!(decoded, receipt);
+    }
+
+    #[tokio::test]
+    async fn test_submit() {
+        let client = fake_client().await;
+        let data = blob_data(b"hello eigenda");
+        let commitment = keccak256(&data.data).to_string();
+
+        assert!(
+            Submitter::submit(&client, &B256::ZERO.to_string(), data.clone())
+                .await
+                .is_err()
+        );
+
+        let receipt = Submitter::submit(&client, &commitment, data.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            receipt,
+            EigenDaReceipt {
+                batch_header_hash: B256::repeat_byte(1),
+                blob_index: 0,
+                data_hash: keccak256(&data.data),
+            }
+        );
+        assert!(Verifier::is_included(&client, &receipt).await.unwrap());
+        Verifier::verify_post(&client, &receipt, &data)
+            .await
+            .unwrap();
+        assert!(
+            Verifier::verify_post(&client, &receipt, &blob_data(b"other"))
+                .await
+                .is_err()
+        );
+
+        let unknown = EigenDaReceipt {
+            batch_header_hash: B256::repeat_byte(9),
+            ..receipt.clone()
+        };
+        assert!(Verifier::is_included(&client, &unknown).await.is_err());
+        let other_data = EigenDaReceipt {
+            data_hash: B256::ZERO,
+            ..receipt
+        };
+        assert!(!Verifier::is_included(&client, &other_data).await.unwrap());
+
+        let flaky = blob_data(&[1, 0xfe]);
+        let commitment = keccak256(&flaky.data).to_string();
+        let receipt = Submitter::submit(&client, &commitment, flaky.clone())
+            .await
+            .unwrap();
+        Verifier::verify_post(&client, &receipt, &flaky)
+            .await
+            .unwrap();
+
+        let failing = blob_data(&[1, 0xff]);
+        let commitment = keccak256(&failing.data).to_string();
+        let error = Submitter::submit(&client, &commitment, failing)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("Failed"));
+
+        Health::health(&client).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_estimate_cost() {
+        let client = fake_client().await;
+        assert_eq!(
+            Estimator::estimate_cost(&client, 0).await.unwrap(),
+            U256::from(10)
+        );
+        assert_eq!(
+            Estimator::estimate_cost(&client, 62).await.unwrap(),
+            U256::from(30)
+        );
+    }
+}
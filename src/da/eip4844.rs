@@ -1,122 +1,332 @@
-use std::{cmp, sync::Arc};
+use std::{cmp, collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
 use alloy::{
-    consensus::{utils::WholeFe, Bytes48, SidecarBuilder, SidecarCoder},
+    consensus::{Blob, Bytes48, SidecarBuilder, SidecarCoder},
+    eips::eip4844::{
+        builder::PartialSidecar, env_settings::EnvKzgSettings, kzg_to_versioned_hash,
+        BlobTransactionSidecar, BLS_MODULUS, DATA_GAS_PER_BLOB, FIELD_ELEMENTS_PER_BLOB,
+        MAX_BLOBS_PER_BLOCK,
+    },
     network::{Ethereum, EthereumWallet, TransactionBuilder, TransactionBuilder4844, TxSigner},
-    primitives::{Address, B256},
+    primitives::{keccak256, Address, Bytes, B256, U256},
     providers::{
         fillers::{
             BlobGasFiller, CachedNonceManager, ChainIdFiller, FillProvider, GasFiller, JoinFill,
             NonceFiller, WalletFiller,
         },
-        Identity, Provider, ProviderBuilder, ReqwestProvider,
+        Identity, PendingTransactionError, Provider, ProviderBuilder, ReqwestProvider,
+        WatchTxError,
     },
-    rpc::types::{BlockTransactionsKind, TransactionRequest},
+    rpc::types::{BlockTransactionsKind, TransactionReceipt, TransactionRequest},
+    sol_types::SolValue,
     transports::http::ReqwestTransport,
 };
 use eyre::OptionExt;
+use metrics::counter;
 use serde::{Deserialize, Serialize};
-use serde_with::{serde_as, DisplayFromStr};
+use serde_with::{formats::PreferMany, serde_as, DisplayFromStr, OneOrMany};
+use tokio::sync::Mutex;
 use url::Url;
 
-use crate::kms::KmsSigner;
+use crate::{
+    contracts::eip4844_verifier::BlobProof,
+    kms::KmsSigner,
+    price::Asset,
+    retry::{classify_message, ErrorClass},
+};
+
+use super::{BlobData, Estimator, Health, Prover, Submitter, Verifier};
 
-use super::{BlobData, Submitter};
+/// Execution gas of a plain transaction carrying the blobs
+const BLOB_TX_GAS: u64 = 21_000;
 
+/// The blobs of a single blob transaction and the slot of the beacon block that included them
+#[serde_as]
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Eip4844Receipt {
     pub beacon_block_slot: u64,
-    pub commitment: Bytes48,
+    /// Commitments of the blobs, in the order of the payload
+    #[serde(alias = "commitment")]
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
+    pub commitments: Vec<Bytes48>,
+}
+
+/// Where the blobs of a payload were posted, one receipt per blob transaction in the order of
+/// the payload. Payloads of more than [`MAX_BLOBS_PER_BLOCK`] blobs take several transactions
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Eip4844Manifest {
+    #[serde_as(as = "OneOrMany<_, PreferMany>")]
+    pub receipts: Vec<Eip4844Receipt>,
 }
 
+impl Eip4844Manifest {
+    /// The commitment of every blob
+    pub fn commitments(&self) -> impl Iterator<Item = &Bytes48> {
+        self.receipts
+            .iter()
+            .flat_map(|receipt| receipt.commitments.iter())
+    }
+
+    /// The slot of every blob
+    pub fn slots(&self) -> Vec<u64> {
+        self.receipts
+            .iter()
+            .flat_map(|receipt| vec![receipt.beacon_block_slot; receipt.commitments.len()])
+            .collect()
+    }
+
+    /// Rebuilds a manifest from the commitment and slot of every blob, grouping the blobs of
+    /// consecutive slots
+    pub fn from_blobs(commitments: Vec<Bytes48>, slots: &[u64]) -> eyre::Result<Self> {
+        if commitments.len() != slots.len() {
+            return Err(eyre::eyre!(
+                "Got {} slots for {} commitments",
+                slots.len(),
+                commitments.len()
+            ));
+        }
+        let mut receipts: Vec<Eip4844Receipt> = Vec::new();
+        for (commitment, &slot) in commitments.into_iter().zip(slots) {
+            match receipts.last_mut() {
+                Some(receipt) if receipt.beacon_block_slot == slot => {
+                    receipt.commitments.push(commitment)
+                }
+                _ => receipts.push(Eip4844Receipt {
+                    beacon_block_slot: slot,
+                    commitments: vec![commitment],
+                }),
+            }
+        }
+        Ok(Self { receipts })
+    }
+
+    /// ABI-encoded context passed to `Kuda::submitReceipt`: the slot of the blobs when they are
+    /// all in one block, and the slot of every blob otherwise. The Kuda EIP-4844 verifier only
+    /// accepts the former, see [`Eip4844Client::multi_blob_payloads`]
+    pub fn context(&self) -> Bytes {
+        match self.receipts.as_slice() {
+            [receipt] => Bytes::copy_from_slice(&receipt.beacon_block_slot.abi_encode()),
+            _ => Bytes::copy_from_slice(&self.slots().abi_encode()),
+        }
+    }
+
+    /// Rebuilds a manifest from the comma-separated `commitment` and `context` of a
+    /// `Kuda::submitReceipt` call
+    pub fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self> {
+        let commitments = parse_commitment_list(commitment)?;
+        let slots = if context.len() == 32 {
+            vec![u64::abi_decode(context, true)?; commitments.len()]
+        } else {
+            Vec::<u64>::abi_decode(context, true)?
+        };
+        Self::from_blobs(commitments, &slots)
+    }
+}
+
+/// The commitment string of a payload: the commitment of every blob, comma-separated
+pub fn commitment_list<'a>(commitments: impl IntoIterator<Item = &'a Bytes48>) -> String {
+    commitments
+        .into_iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+pub fn parse_commitment_list(commitments: &str) -> eyre::Result<Vec<Bytes48>> {
+    commitments
+        .split(',')
+        .map(|commitment| Ok(Bytes48::from_str(commitment.trim())?))
+        .collect()
+}
+
+/// Payload bytes carried by a field element, whose first byte must stay zero
+const FE_DATA_SIZE: usize = 31;
+
+/// Version byte opening every payload coded with the [`BlobCoder`]
+pub const BLOB_FORMAT_VERSION: u8 = 1;
+
+/// Size of the [`BlobCoder`] header: the version byte and the data length as a big-endian u64
+const HEADER_SIZE: usize = 9;
+
+/// Codes each payload as a header, holding [`BLOB_FORMAT_VERSION`] and the data length, followed
+/// by the data, 31 bytes per field element. Every payload starts on a new field element, and the
+/// zero padding after the last one reads as version 0, which ends decoding
 #[derive(Clone, Copy, Debug, Default)]
-pub struct TerminationCoder(pub usize);
-
-impl TerminationCoder {
-    /// Decode an some bytes from an iterator of valid FEs.
-    ///
-    /// Returns `Ok(Some(data))` if there is some data.
-    /// Returns `Ok(None)` if there is no data (length prefix is 0).
-    /// Returns `Err(_)` if there is an error.
-    fn decode_one<'a>(fes: impl Iterator<Item = WholeFe<'a>>) -> eyre::Result<Option<Vec<u8>>> {
-        let mut res = Vec::new();
-        let regex = regex::bytes::Regex::new(r"(?-u)\x80[\x00]*")?;
-
-        // This is very inefficient, but we won't be using decoding primarily
-        // so it should be fine
-        for fe in fes {
-            let bytes = &fe.as_ref()[1..32];
-            if let Some(mid) = regex.find(bytes).map(|mid| mid.start()) {
-                let to_append = bytes.split_at(mid).0;
-                res.extend_from_slice(to_append);
-                break;
-            } else {
-                tracing::warn!("No match");
-                res.extend_from_slice(bytes);
+pub struct BlobCoder;
+
+impl SidecarCoder for BlobCoder {
+    fn required_fe(&self, _: &[u8]) -> usize {
+        // Allocated by `ingest_fe` while coding, see there
+        0
+    }
+
+    fn code(&mut self, builder: &mut PartialSidecar, data: &[u8]) {
+        let mut header = [0u8; HEADER_SIZE];
+        header[0] = BLOB_FORMAT_VERSION;
+        header[1..].copy_from_slice(&(data.len() as u64).to_be_bytes());
+        for chunk in [&header[..], data].concat().chunks(FE_DATA_SIZE) {
+            ingest_fe(builder, chunk);
+        }
+    }
+
+    fn finish(self, _: &mut PartialSidecar) {}
+
+    fn decode_all(&mut self, blobs: &[Blob]) -> Option<Vec<Vec<u8>>> {
+        let fes = fe_data(blobs)?;
+        let mut payloads = Vec::new();
+        let mut index = 0;
+        while let Some(first) = fes.get(index) {
+            match first[0] {
+                0 => break,
+                BLOB_FORMAT_VERSION => {}
+                _ => return None,
             }
+            // The length is read from the blobs, so a corrupted header must not overflow
+            let length = u64::from_be_bytes(first[1..HEADER_SIZE].try_into().ok()?);
+            let end = HEADER_SIZE.checked_add(usize::try_from(length).ok()?)?;
+            let fe_count = end.div_ceil(FE_DATA_SIZE);
+            let payload_fes = fes.get(index..index.checked_add(fe_count)?)?;
+            let payload = payload_fes.concat();
+            payloads.push(payload.get(HEADER_SIZE..end)?.to_vec());
+            index += fe_count;
         }
-        Ok(Some(res))
+        Some(payloads)
     }
 }
 
+/// The format of payloads posted before the [`BlobCoder`]: the data, 31 bytes per field element,
+/// followed by a 0x80 terminator. Only a single payload can be decoded, as the terminator is then
+/// the last non-zero byte of the blobs
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TerminationCoder;
+
 impl SidecarCoder for TerminationCoder {
-    fn required_fe(&self, data: &[u8]) -> usize {
-        data.len().div_ceil(31)
+    fn required_fe(&self, _: &[u8]) -> usize {
+        // Allocated by `ingest_fe` while coding, see there
+        0
     }
 
-    fn code(
-        &mut self,
-        builder: &mut alloy::eips::eip4844::builder::PartialSidecar,
-        mut data: &[u8],
-    ) {
-        // ingest the rest of the data
-        loop {
-            let mid = cmp::min(31, data.len());
-            let (left, right) = data.split_at(mid);
-            self.0 += mid;
-            // If we have less than 31 bytes, we can stop processing
-            // and push a terminator byte to indicate the end of the blob.
-            if left.len() < 31 {
-                let mut to_ingest = left.to_vec();
-                to_ingest.push(0x80);
-                builder.ingest_partial_fe(&to_ingest);
-                return;
-            } else {
-                builder.ingest_partial_fe(left);
-            }
-            data = right
+    fn code(&mut self, builder: &mut PartialSidecar, data: &[u8]) {
+        // The terminator takes a field element of its own after a whole number of them
+        let mut chunks = data.chunks_exact(FE_DATA_SIZE);
+        for chunk in chunks.by_ref() {
+            ingest_fe(builder, chunk);
         }
+        ingest_fe(builder, &[chunks.remainder(), &[0x80]].concat());
     }
 
-    fn decode_all(&mut self, blobs: &[alloy::consensus::Blob]) -> Option<Vec<Vec<u8>>> {
-        let mut fes = blobs
-            .iter()
-            .flat_map(|blob| blob.chunks(32).map(WholeFe::new))
-            .map(Option::unwrap);
-        let mut res = Vec::new();
-        let mut decoded_length = 0;
-        loop {
-            match Self::decode_one(&mut fes) {
-                Ok(Some(data)) => {
-                    decoded_length += data.len();
-                    res.push(data);
-                    if decoded_length >= self.0 {
-                        break;
-                    }
-                }
-                Ok(None) => break,
-                Err(_) => return None,
-            }
+    fn finish(self, _: &mut PartialSidecar) {}
+
+    fn decode_all(&mut self, blobs: &[Blob]) -> Option<Vec<Vec<u8>>> {
+        let mut data = fe_data(blobs)?.concat();
+        let terminator = data.iter().rposition(|&byte| byte != 0)?;
+        if data[terminator] != 0x80 {
+            return None;
         }
-        tracing::info!(
-            "Coded length: {}, decoded length: {}",
-            self.0,
-            decoded_length
-        );
-        Some(res)
+        data.truncate(terminator);
+        Some(vec![data])
     }
+}
 
-    fn finish(self, _: &mut alloy::eips::eip4844::builder::PartialSidecar) {}
+/// Ingests `chunk` as the next field element. `PartialSidecar` always writes to its last blob, so
+/// blobs allocated ahead of the data would be filled from the last one and overwrite each other.
+/// Allocating a field element at a time only adds a blob once the current one is full
+fn ingest_fe(builder: &mut PartialSidecar, chunk: &[u8]) {
+    builder.alloc_fes(1);
+    builder.ingest_partial_fe(chunk);
+}
+
+/// The payload bytes of every field element of `blobs`, or `None` if one of them is invalid
+fn fe_data(blobs: &[Blob]) -> Option<Vec<&[u8]>> {
+    blobs
+        .iter()
+        .flat_map(|blob| blob.chunks(32))
+        .map(|fe| (fe[0] == 0).then(|| &fe[1..]))
+        .collect()
+}
+
+/// Codes `data` in the format the `provided_commitment` was computed with: the [`BlobCoder`], or
+/// the [`TerminationCoder`] of older clients
+pub fn build_sidecar(
+    data: &[u8],
+    provided_commitment: &str,
+) -> eyre::Result<BlobTransactionSidecar> {
+    let sidecar = SidecarBuilder::<BlobCoder>::from_slice(data).build()?;
+    let computed_commitment = commitment_list(&sidecar.commitments);
+    if provided_commitment == computed_commitment {
+        return Ok(sidecar);
+    }
+
+    let legacy_sidecar = SidecarBuilder::<TerminationCoder>::from_slice(data).build()?;
+    if provided_commitment == commitment_list(&legacy_sidecar.commitments) {
+        tracing::debug!("[EIP4844] Coding payload in the termination format");
+        return Ok(legacy_sidecar);
+    }
+
+    Err(eyre::eyre!(
+        "Provided commitment does not match computed commitment {provided_commitment} != {computed_commitment}"
+    ))
+}
+
+/// Number of blobs needed to hold `size` bytes with the [`BlobCoder`]
+pub fn blobs_for_size(size: u64) -> u64 {
+    (HEADER_SIZE as u64 + size)
+        .div_ceil(FE_DATA_SIZE as u64)
+        .div_ceil(FIELD_ELEMENTS_PER_BLOB)
+}
+
+/// Checks that the blob of `sidecar` matches its KZG commitment and proof
+pub fn verify_sidecar(sidecar: &BlobSidecar) -> eyre::Result<()> {
+    let blob = c_kzg::Blob::from_bytes(sidecar.blob.as_slice())?;
+    let commitment = c_kzg::Bytes48::from_bytes(sidecar.kzg_commitment.as_slice())?;
+    let proof = c_kzg::Bytes48::from_bytes(sidecar.kzg_proof.as_slice())?;
+    if !c_kzg::KzgProof::verify_blob_kzg_proof(
+        &blob,
+        &commitment,
+        &proof,
+        EnvKzgSettings::Default.get(),
+    )? {
+        return Err(eyre::eyre!(
+            "Invalid KZG proof for blob with commitment {}",
+            sidecar.kzg_commitment
+        ));
+    }
+    Ok(())
+}
+
+/// Builds the proof the `eip4844Verifier` checks for the blob of `sidecar`: an opening of the
+/// commitment at a point derived from it, and the inclusion of the commitment in the block
+pub fn blob_proof(sidecar: &BlobSidecar) -> eyre::Result<BlobProof> {
+    let settings = EnvKzgSettings::Default.get();
+    let versioned_hash = kzg_to_versioned_hash(sidecar.kzg_commitment.as_slice());
+    // Any point works for the precompile, deriving it from the commitment keeps it reproducible
+    let z = U256::from_be_bytes(keccak256(versioned_hash).0).reduce_mod(BLS_MODULUS);
+    let z = c_kzg::Bytes32::new(z.to_be_bytes());
+
+    let blob = c_kzg::Blob::from_bytes(sidecar.blob.as_slice())?;
+    let commitment = c_kzg::Bytes48::from_bytes(sidecar.kzg_commitment.as_slice())?;
+    let (proof, y) = c_kzg::KzgProof::compute_kzg_proof(&blob, &z, settings)?;
+    let proof = proof.to_bytes();
+    if !c_kzg::KzgProof::verify_kzg_proof(&commitment, &z, &y, &proof, settings)? {
+        return Err(eyre::eyre!(
+            "Blob does not match commitment {}",
+            sidecar.kzg_commitment
+        ));
+    }
+
+    Ok(BlobProof {
+        slot: sidecar.signed_block_header.message.slot,
+        index: sidecar.index,
+        versionedHash: versioned_hash,
+        z: B256::from_slice(z.as_slice()),
+        y: B256::from_slice(y.as_slice()),
+        commitment: Bytes::copy_from_slice(sidecar.kzg_commitment.as_slice()),
+        proof: Bytes::copy_from_slice(proof.as_slice()),
+        bodyRoot: sidecar.signed_block_header.message.body_root,
+        commitmentInclusionProof: sidecar.kzg_commitment_inclusion_proof.clone(),
+    })
 }
 
 /// The response to a request for a __single__ beacon block: `blocks/{id}`
@@ -157,6 +367,76 @@ pub struct ExecutionPayload {
     pub block_hash: B256,
 }
 
+/// The response to a request for the blob sidecars of a block: `blob_sidecars/{id}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobSidecarsResponse {
+    pub data: Vec<BlobSidecar>,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlobSidecar {
+    /// The index of the blob in the block.
+    #[serde_as(as = "DisplayFromStr")]
+    pub index: u64,
+    pub blob: Box<Blob>,
+    pub kzg_commitment: Bytes48,
+    pub kzg_proof: Bytes48,
+    pub signed_block_header: SignedBeaconBlockHeader,
+    /// Merkle proof of the commitment in the body of the block
+    pub kzg_commitment_inclusion_proof: Vec<B256>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedBeaconBlockHeader {
+    pub message: BeaconBlockHeader,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BeaconBlockHeader {
+    #[serde_as(as = "DisplayFromStr")]
+    pub slot: u64,
+    pub body_root: B256,
+}
+
+/// The response to a request for a single beacon block header: `headers/{id}`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderResponse {
+    pub data: HeaderData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderData {
+    pub header: SignedHeader,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedHeader {
+    pub message: HeaderMessage,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeaderMessage {
+    #[serde_as(as = "DisplayFromStr")]
+    pub slot: u64,
+}
+
+/// The response to `node/syncing`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncingResponse {
+    pub data: SyncingData,
+}
+
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncingData {
+    #[serde_as(as = "DisplayFromStr")]
+    pub sync_distance: u64,
+    pub is_syncing: bool,
+}
+
 type RecommendedProvider = FillProvider<
     JoinFill<
         JoinFill<
@@ -173,12 +453,37 @@ type RecommendedProvider = FillProvider<
     Ethereum,
 >;
 
+/// How blob transactions that are not included in time get replaced
+#[derive(Debug, Clone)]
+pub struct ReplacementConfig {
+    /// How long to wait for a blob transaction to be included before replacing it
+    pub inclusion_timeout: Duration,
+    /// Maximum number of times a blob transaction is replaced with bumped fees
+    pub max_replacements: usize,
+}
+
 pub struct Eip4844Client {
     from: Address,
     to: Address,
     provider: RecommendedProvider,
     beacon_url: Url,
     reqwest_client: reqwest::Client,
+    replacement: ReplacementConfig,
+    /// Whether to post payloads of more than one blob. Their comma-separated commitments and
+    /// slot list context are not accepted by the Kuda EIP-4844 verifier yet, so their receipts
+    /// can't be proven when challenged
+    pub multi_blob_payloads: bool,
+    /// What failed posts left behind, by payload commitment, so that a retry resumes from them
+    partial: Mutex<HashMap<String, PartialPost>>,
+}
+
+/// The progress of a failed post of a payload
+#[derive(Debug, Default)]
+struct PartialPost {
+    /// Receipts of the transactions mined, in order
+    receipts: Vec<Eip4844Receipt>,
+    /// Hashes of the transactions sent for the next blobs, one of which may still be mined
+    sent: Vec<B256>,
 }
 
 impl Eip4844Client {
@@ -187,6 +492,8 @@ impl Eip4844Client {
         to: Address,
         rpc_url: Url,
         beacon_url: Url,
+        replacement: ReplacementConfig,
+        multi_blob_payloads: bool,
     ) -> eyre::Result<Self> {
         let filler = JoinFill::new(
             GasFiller,
@@ -209,40 +516,194 @@ impl Eip4844Client {
             provider,
             beacon_url,
             reqwest_client: reqwest::Client::new(),
+            replacement,
+            multi_blob_payloads,
+            partial: Mutex::new(HashMap::new()),
         })
     }
-}
 
-impl Submitter for Eip4844Client {
-    type Receipt = Eip4844Receipt;
-
-    async fn submit(
+    /// Sends a blob transaction and waits for it to be mined, replacing it with bumped fees
+    /// whenever it is not included within the inclusion timeout. The hashes of the transactions
+    /// sent are added to `sent`. A non-empty `sent` comes from a previous attempt, which is
+    /// resumed: the blobs are only sent again once all of its transactions were dropped
+    async fn send_blob_transaction(
         &self,
-        provided_commitment: &str,
-        blob_data: BlobData,
-    ) -> eyre::Result<Self::Receipt> {
-        // Create a sidecar with some data.
-        let sidecar: SidecarBuilder<TerminationCoder> = SidecarBuilder::from_slice(&blob_data.data);
-        let sidecar = sidecar.build()?;
-        let commitment = sidecar.commitments[0];
-        if provided_commitment != commitment.to_string() {
-            return Err(eyre::eyre!(
-                "Provided commitment does not match computed commitment {provided_commitment} != {}", commitment.to_string()
-            ));
+        mut tx: TransactionRequest,
+        sent: &mut Vec<B256>,
+    ) -> eyre::Result<TransactionReceipt> {
+        if !sent.is_empty() {
+            for tx_hash in sent.iter() {
+                if let Some(receipt) = self.provider.get_transaction_receipt(*tx_hash).await? {
+                    return Ok(receipt);
+                }
+            }
+            for tx_hash in sent.iter() {
+                if self
+                    .provider
+                    .get_transaction_by_hash(*tx_hash)
+                    .await?
+                    .is_some()
+                {
+                    return Err(eyre::eyre!(
+                        "Blob transaction {tx_hash} of a previous attempt is still pending"
+                    ));
+                }
+            }
+            tracing::warn!(
+                "[EIP4844] Blob transactions {sent:?} of a previous attempt were dropped, sending again"
+            );
+            sent.clear();
+        }
+
+        let mut pending = match self.provider.send_transaction(tx.clone()).await {
+            Ok(pending) => pending,
+            Err(e) if classify_message(&e.to_string()) == ErrorClass::NonceConflict => {
+                // The cached nonce is out of sync with the chain, fall back to the pending nonce
+                tracing::warn!("[EIP4844] Nonce conflict, resending with the pending nonce: {e}");
+                tx.nonce = Some(self.pending_nonce().await?);
+                self.provider.send_transaction(tx.clone()).await?
+            }
+            Err(e) => return Err(e.into()),
+        };
+        sent.push(*pending.tx_hash());
+
+        loop {
+            match pending
+                .with_timeout(Some(self.replacement.inclusion_timeout))
+                .get_receipt()
+                .await
+            {
+                Ok(receipt) => return Ok(receipt),
+                Err(PendingTransactionError::TxWatcher(WatchTxError::Timeout)) => {}
+                Err(e) => return Err(e.into()),
+            }
+
+            // One of the transactions we replaced may have been mined in the meantime
+            for tx_hash in sent.iter() {
+                if let Some(receipt) = self.provider.get_transaction_receipt(*tx_hash).await? {
+                    return Ok(receipt);
+                }
+            }
+
+            let last_tx_hash = sent[sent.len() - 1];
+            if sent.len() > self.replacement.max_replacements {
+                return Err(eyre::eyre!(
+                    "Blob transaction {last_tx_hash} not included after {} replacements",
+                    self.replacement.max_replacements
+                ));
+            }
+
+            self.bump_fees(&mut tx, last_tx_hash).await?;
+            pending = self.provider.send_transaction(tx.clone()).await?;
+            sent.push(*pending.tx_hash());
+            counter!("eip4844_tx_replaced").increment(1);
+            tracing::warn!(
+                "[EIP4844] Blob transaction {last_tx_hash} not included within {:?}, replaced with {}",
+                self.replacement.inclusion_timeout,
+                pending.tx_hash()
+            );
+        }
+    }
+
+    /// Prices `tx` to replace the transaction `tx_hash`, at least doubling every fee as the
+    /// blob pool requires
+    async fn bump_fees(&self, tx: &mut TransactionRequest, tx_hash: B256) -> eyre::Result<()> {
+        let fees = self.provider.estimate_eip1559_fees(None).await?;
+        let blob_base_fee = self.provider.get_blob_base_fee().await?;
+
+        match self.provider.get_transaction_by_hash(tx_hash).await? {
+            Some(sent) => {
+                let bump = |previous: Option<u128>, current: u128| {
+                    cmp::max(previous.unwrap_or_default().saturating_mul(2), current)
+                };
+                tx.nonce = Some(sent.nonce);
+                tx.gas = Some(sent.gas);
+                tx.max_fee_per_gas = Some(bump(sent.max_fee_per_gas, fees.max_fee_per_gas));
+                tx.max_priority_fee_per_gas = Some(bump(
+                    sent.max_priority_fee_per_gas,
+                    fees.max_priority_fee_per_gas,
+                ));
+                tx.max_fee_per_blob_gas = Some(bump(
+                    sent.max_fee_per_blob_gas,
+                    blob_base_fee.saturating_mul(2),
+                ));
+            }
+            None => {
+                // The transaction was dropped, so its nonce is free again
+                tx.nonce = Some(self.pending_nonce().await?);
+                tx.max_fee_per_gas = Some(fees.max_fee_per_gas);
+                tx.max_priority_fee_per_gas = Some(fees.max_priority_fee_per_gas);
+                tx.max_fee_per_blob_gas = Some(blob_base_fee.saturating_mul(2));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetches the sidecars of the blobs behind `receipt` from the beacon node, in the order of
+    /// its commitments, and checks their KZG proofs, confirming the blobs were published at the
+    /// receipt slot
+    pub async fn blob_sidecars(&self, receipt: &Eip4844Receipt) -> eyre::Result<Vec<BlobSidecar>> {
+        let mut sidecars = self
+            .slot_sidecars(receipt.beacon_block_slot)
+            .await?
+            .ok_or_eyre("No block at receipt slot")?;
+
+        let mut found = Vec::with_capacity(receipt.commitments.len());
+        for commitment in &receipt.commitments {
+            let position = sidecars
+                .iter()
+                .position(|sidecar| sidecar.kzg_commitment == *commitment)
+                .ok_or_eyre(format!(
+                    "Commitment {commitment} not found in blob sidecars"
+                ))?;
+            let sidecar = sidecars.swap_remove(position);
+            verify_sidecar(&sidecar)?;
+            tracing::info!(
+                "[EIP4844] Found blob with commitment {commitment} at slot {} and index {}",
+                receipt.beacon_block_slot,
+                sidecar.index
+            );
+            found.push(sidecar);
         }
+        Ok(found)
+    }
 
+    /// The blob sidecars of the block at `slot`, or `None` if no block was proposed at the slot
+    async fn slot_sidecars(&self, slot: u64) -> eyre::Result<Option<Vec<BlobSidecar>>> {
+        let response = self
+            .reqwest_client
+            .get(
+                self.beacon_url
+                    .join("eth/v1/beacon/blob_sidecars/")?
+                    .join(&slot.to_string())?,
+            )
+            .send()
+            .await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let sidecars = response
+            .error_for_status()?
+            .json::<BlobSidecarsResponse>()
+            .await?;
+        Ok(Some(sidecars.data))
+    }
+
+    /// Sends the blobs of `sidecar` in one transaction and finds the slot it was included at.
+    /// See [`Self::send_blob_transaction`] for `sent`
+    async fn submit_transaction(
+        &self,
+        sidecar: BlobTransactionSidecar,
+        sent: &mut Vec<B256>,
+    ) -> eyre::Result<Eip4844Receipt> {
+        let commitments = sidecar.commitments.clone();
         let tx = TransactionRequest::default()
             .with_from(self.from)
             .with_to(self.to)
             .with_blob_sidecar(sidecar);
 
         // Send the transaction and wait for the receipt.
-        let receipt = self
-            .provider
-            .send_transaction(tx)
-            .await?
-            .get_receipt()
-            .await?;
+        let receipt = self.send_blob_transaction(tx, sent).await?;
 
         let block_hash = receipt.block_hash.ok_or_eyre("No block hash in receipt")?;
         let block = self
@@ -282,8 +743,11 @@ impl Submitter for Eip4844Client {
         let beacon_block_slot = parent_beacon_block.data.message.slot + slot_difference;
 
         let tx_hash = receipt.transaction_hash;
-        let mut msg =
-            format!("[EIP4844] Submitted blob with commitment {commitment} with transaction hash: {tx_hash}");
+        let mut msg = format!(
+            "[EIP4844] Submitted {} blobs with commitments {} with transaction hash: {tx_hash}",
+            commitments.len(),
+            commitment_list(&commitments)
+        );
         if let Some(block_number) = receipt.block_number {
             msg.push_str(&format!(
                 " at block number: {block_number} and slot: {beacon_block_slot}"
@@ -291,38 +755,435 @@ impl Submitter for Eip4844Client {
         }
         tracing::info!("{msg}");
 
-        let receipt = Eip4844Receipt {
+        Ok(Eip4844Receipt {
             beacon_block_slot,
-            commitment,
-        };
-        Ok(receipt)
+            commitments,
+        })
+    }
+
+    async fn pending_nonce(&self) -> eyre::Result<u64> {
+        Ok(self
+            .provider
+            .get_transaction_count(self.from)
+            .pending()
+            .await?)
+    }
+}
+
+impl Submitter for Eip4844Client {
+    type Receipt = Eip4844Manifest;
+
+    /// Posts the blobs of the payload, at most [`MAX_BLOBS_PER_BLOCK`] per transaction. When a
+    /// transaction fails, the retry of the post resumes from it, waiting for the transaction
+    /// sent before if it was not dropped
+    async fn submit(
+        &self,
+        provided_commitment: &str,
+        blob_data: BlobData,
+    ) -> eyre::Result<Self::Receipt> {
+        let sidecar = build_sidecar(&blob_data.data, provided_commitment)?;
+        if sidecar.blobs.len() > 1 && !self.multi_blob_payloads {
+            return Err(eyre::eyre!(
+                "Payload of {} bytes needs {} blobs, multi-blob payloads are disabled",
+                blob_data.data.len(),
+                sidecar.blobs.len()
+            ));
+        }
+
+        let PartialPost {
+            mut receipts,
+            mut sent,
+        } = self
+            .partial
+            .lock()
+            .await
+            .remove(provided_commitment)
+            .unwrap_or_default();
+        if !receipts.is_empty() || !sent.is_empty() {
+            tracing::info!(
+                "[EIP4844] Resuming post of {provided_commitment} after {} mined and {} sent transactions",
+                receipts.len(),
+                sent.len()
+            );
+        }
+        for ((blobs, commitments), proofs) in sidecar
+            .blobs
+            .chunks(MAX_BLOBS_PER_BLOCK)
+            .zip(sidecar.commitments.chunks(MAX_BLOBS_PER_BLOCK))
+            .zip(sidecar.proofs.chunks(MAX_BLOBS_PER_BLOCK))
+            .skip(receipts.len())
+        {
+            let sidecar = BlobTransactionSidecar {
+                blobs: blobs.to_vec(),
+                commitments: commitments.to_vec(),
+                proofs: proofs.to_vec(),
+            };
+            match self.submit_transaction(sidecar, &mut sent).await {
+                Ok(receipt) => {
+                    receipts.push(receipt);
+                    sent.clear();
+                }
+                Err(e) => {
+                    // The transaction may have been mined before the error, posting its blobs
+                    // again on retry would pay for them twice
+                    if !receipts.is_empty() || !sent.is_empty() {
+                        self.partial.lock().await.insert(
+                            provided_commitment.to_string(),
+                            PartialPost { receipts, sent },
+                        );
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        Ok(Eip4844Manifest { receipts })
+    }
+}
+
+impl Estimator for Eip4844Client {
+    const NATIVE_ASSET: Asset = Asset::Eth;
+
+    async fn estimate_cost(&self, size: u64) -> eyre::Result<U256> {
+        let blob_base_fee = self.provider.get_blob_base_fee().await?;
+        let fees = self.provider.estimate_eip1559_fees(None).await?;
+        let blobs = blobs_for_size(size);
+        if blobs > 1 && !self.multi_blob_payloads {
+            return Err(eyre::eyre!(
+                "Payload of {size} bytes needs {blobs} blobs, multi-blob payloads are disabled"
+            ));
+        }
+        let transactions = blobs.div_ceil(MAX_BLOBS_PER_BLOCK as u64).max(1);
+        Ok(
+            U256::from(blobs * DATA_GAS_PER_BLOB) * U256::from(blob_base_fee)
+                + U256::from(transactions * BLOB_TX_GAS) * U256::from(fees.max_fee_per_gas),
+        )
+    }
+}
+
+impl Prover for Eip4844Client {
+    type Receipt = Eip4844Manifest;
+
+    /// Proves every blob of the payload, in order
+    async fn inclusion_proof(&self, manifest: &Self::Receipt) -> eyre::Result<Bytes> {
+        let mut proofs = Vec::new();
+        for receipt in &manifest.receipts {
+            for sidecar in self.blob_sidecars(receipt).await? {
+                proofs.push(blob_proof(&sidecar)?);
+            }
+        }
+        Ok(proofs.abi_encode().into())
+    }
+}
+
+impl Verifier for Eip4844Client {
+    type Receipt = Eip4844Manifest;
+
+    async fn is_included(&self, manifest: &Self::Receipt) -> eyre::Result<bool> {
+        let head_slot = self
+            .reqwest_client
+            .get(self.beacon_url.join("eth/v1/beacon/headers/head")?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<HeaderResponse>()
+            .await?
+            .data
+            .header
+            .message
+            .slot;
+        let last_slot = manifest.slots().into_iter().max().unwrap_or_default();
+        if last_slot > head_slot {
+            return Err(eyre::eyre!(
+                "Beacon node is at slot {head_slot}, behind receipt slot {last_slot}"
+            ));
+        }
+
+        for receipt in &manifest.receipts {
+            // No block was proposed at the slot
+            let Some(sidecars) = self.slot_sidecars(receipt.beacon_block_slot).await? else {
+                return Ok(false);
+            };
+            let included = receipt.commitments.iter().all(|commitment| {
+                sidecars
+                    .iter()
+                    .any(|sidecar| sidecar.kzg_commitment == *commitment)
+            });
+            if !included {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn verify_post(&self, manifest: &Self::Receipt, data: &BlobData) -> eyre::Result<()> {
+        let mut blobs = Vec::new();
+        for receipt in &manifest.receipts {
+            for sidecar in self.blob_sidecars(receipt).await? {
+                blobs.push(*sidecar.blob);
+            }
+        }
+        let round_trips =
+            |payloads: Option<Vec<Vec<u8>>>| payloads == Some(vec![data.data.clone()]);
+        if !round_trips(BlobCoder.decode_all(&blobs))
+            && !round_trips(TerminationCoder.decode_all(&blobs))
+        {
+            return Err(eyre::eyre!(
+                "Blobs read back at slots {:?} differ from the posted data",
+                manifest.slots()
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Health for Eip4844Client {
+    async fn health(&self) -> eyre::Result<()> {
+        self.provider.get_block_number().await?;
+        let syncing = self
+            .reqwest_client
+            .get(self.beacon_url.join("eth/v1/node/syncing")?)
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<SyncingResponse>()
+            .await?
+            .data;
+        if syncing.is_syncing {
+            return Err(eyre::eyre!(
+                "Beacon node is syncing, {} slots behind",
+                syncing.sync_distance
+            ));
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use alloy::eips::eip4844::BYTES_PER_BLOB;
+    use proptest::{collection::vec, prelude::*};
+
+    use crate::retry::classify;
+
     use super::*;
 
     #[test]
     fn test_termination_coder() {
         let data = b"hello world".to_vec();
-
-        let mut builder = alloy::eips::eip4844::builder::PartialSidecar::default();
-        let mut coder = TerminationCoder::default();
-        coder.code(&mut builder, &data);
-        coder.finish(&mut builder);
-        assert_eq!(coder.0, data.len());
-        let blobs = builder.blobs();
-        let decoded = coder.decode_all(blobs).unwrap();
-        assert_eq!(decoded[0], data);
-
         let sidecar: SidecarBuilder<TerminationCoder> = SidecarBuilder::from_slice(&data);
         let blobs = sidecar.clone().take();
-        let blob = blobs[0];
         let mut expected_blob = vec![0u8];
         expected_blob.extend_from_slice(&data);
-        assert_eq!(&blob[..12], expected_blob);
+        expected_blob.push(0x80);
+        assert_eq!(&blobs[0][..13], expected_blob);
+        assert_eq!(TerminationCoder.decode_all(&blobs), Some(vec![data]));
         let commitment = sidecar.build().unwrap().commitments[0].to_string();
         assert_eq!(commitment, "0xb93ab7583ad8a57b2edd262889391f37a83ab41107dc02c1a68220841379ae828343e84ac1c70fb7c2640ee3522c4c36");
     }
+
+    #[test]
+    fn test_blob_coder() {
+        let data = b"hello world".to_vec();
+        let blobs = SidecarBuilder::<BlobCoder>::from_slice(&data).take();
+        assert_eq!(blobs[0][1], BLOB_FORMAT_VERSION);
+        assert_eq!(&blobs[0][2..10], 11u64.to_be_bytes());
+        assert_eq!(&blobs[0][10..21], data);
+        assert_eq!(BlobCoder.decode_all(&blobs), Some(vec![data.clone()]));
+
+        // Blobs of another format version are not decoded
+        let mut blobs = blobs;
+        blobs[0][1] = BLOB_FORMAT_VERSION + 1;
+        assert_eq!(BlobCoder.decode_all(&blobs), None);
+
+        // Nor are blobs whose header claims more data than they hold
+        for length in [u64::MAX, 1 << 40] {
+            blobs[0][1] = BLOB_FORMAT_VERSION;
+            blobs[0][2..10].copy_from_slice(&length.to_be_bytes());
+            assert_eq!(BlobCoder.decode_all(&blobs), None);
+        }
+
+        // Either format is accepted, as long as the commitment matches
+        let commitment = |sidecar: BlobTransactionSidecar| commitment_list(&sidecar.commitments);
+        let blob_commitment = commitment(
+            SidecarBuilder::<BlobCoder>::from_slice(&data)
+                .build()
+                .unwrap(),
+        );
+        let legacy_commitment = commitment(
+            SidecarBuilder::<TerminationCoder>::from_slice(&data)
+                .build()
+                .unwrap(),
+        );
+        assert_ne!(blob_commitment, legacy_commitment);
+        for provided_commitment in [&blob_commitment, &legacy_commitment] {
+            let sidecar = build_sidecar(&data, provided_commitment).unwrap();
+            assert_eq!(&commitment(sidecar), provided_commitment);
+        }
+        let error = build_sidecar(b"other data", &blob_commitment).unwrap_err();
+        assert_eq!(classify(&error), ErrorClass::Fatal);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn test_coders_round_trip(
+            data in vec(any::<u8>(), 0..3 * BYTES_PER_BLOB),
+            tail in vec(prop_oneof![Just(0u8), Just(0x80u8)], 0..4),
+        ) {
+            // Data ending like a terminator must come back intact
+            let data = [data, tail].concat();
+
+            let blobs = SidecarBuilder::<BlobCoder>::from_slice(&data).take();
+            prop_assert_eq!(blobs.len() as u64, blobs_for_size(data.len() as u64));
+            prop_assert_eq!(BlobCoder.decode_all(&blobs), Some(vec![data.clone()]));
+
+            let blobs = SidecarBuilder::<TerminationCoder>::from_slice(&data).take();
+            prop_assert_eq!(TerminationCoder.decode_all(&blobs), Some(vec![data]));
+        }
+
+        #[test]
+        fn test_blob_coder_payloads(payloads in vec(vec(any::<u8>(), 0..1000), 0..8)) {
+            let mut builder = SidecarBuilder::from_coder_and_capacity(BlobCoder, 1);
+            for payload in &payloads {
+                builder.ingest(payload);
+            }
+            prop_assert_eq!(BlobCoder.decode_all(&builder.take()), Some(payloads));
+        }
+    }
+
+    #[test]
+    fn test_blob_proof() {
+        let built: SidecarBuilder<TerminationCoder> = SidecarBuilder::from_slice(b"hello world");
+        let built = built.build().unwrap();
+        let mut sidecar = BlobSidecar {
+            index: 2,
+            blob: Box::new(built.blobs[0]),
+            kzg_commitment: built.commitments[0],
+            kzg_proof: built.proofs[0],
+            signed_block_header: SignedBeaconBlockHeader {
+                message: BeaconBlockHeader {
+                    slot: 100,
+                    body_root: B256::repeat_byte(1),
+                },
+            },
+            kzg_commitment_inclusion_proof: vec![B256::repeat_byte(2); 17],
+        };
+        verify_sidecar(&sidecar).unwrap();
+
+        let proof = blob_proof(&sidecar).unwrap();
+        assert_eq!((proof.slot, proof.index), (100, 2));
+        assert_eq!(
+            proof.versionedHash,
+            kzg_to_versioned_hash(built.commitments[0].as_slice())
+        );
+        assert_eq!(proof.commitmentInclusionProof.len(), 17);
+        assert!(c_kzg::KzgProof::verify_kzg_proof(
+            &c_kzg::Bytes48::from_bytes(&proof.commitment).unwrap(),
+            &c_kzg::Bytes32::new(proof.z.0),
+            &c_kzg::Bytes32::new(proof.y.0),
+            &c_kzg::Bytes48::from_bytes(&proof.proof).unwrap(),
+            EnvKzgSettings::Default.get(),
+        )
+        .unwrap());
+
+        // A blob that does not match the commitment is rejected
+        sidecar.blob[1] ^= 1;
+        assert!(verify_sidecar(&sidecar).is_err());
+        assert!(blob_proof(&sidecar).is_err());
+    }
+
+    #[test]
+    fn test_manifest_serde() {
+        // Receipts journaled before payloads could span several blobs
+        let commitment = Bytes48::repeat_byte(7);
+        let legacy = format!(r#"{{"beacon_block_slot":42,"commitment":"{commitment}"}}"#);
+        let manifest: Eip4844Manifest = serde_json::from_str(&legacy).unwrap();
+        assert_eq!(
+            manifest,
+            Eip4844Manifest {
+                receipts: vec![Eip4844Receipt {
+                    beacon_block_slot: 42,
+                    commitments: vec![commitment],
+                }],
+            }
+        );
+
+        let manifest = Eip4844Manifest::from_blobs(
+            vec![commitment, Bytes48::repeat_byte(8), Bytes48::repeat_byte(9)],
+            &[42, 42, 43],
+        )
+        .unwrap();
+        assert_eq!(manifest.receipts.len(), 2);
+        assert_eq!(manifest.slots(), vec![42, 42, 43]);
+        let json = serde_json::to_string(&manifest).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Eip4844Manifest>(&json).unwrap(),
+            manifest
+        );
+        assert_eq!(
+            parse_commitment_list(&commitment_list(manifest.commitments())).unwrap(),
+            manifest.commitments().copied().collect::<Vec<_>>()
+        );
+    }
+
+    fn eip4844_receipt(beacon_block_slot: u64, commitments: &[u8]) -> Eip4844Receipt {
+        Eip4844Receipt {
+            beacon_block_slot,
+            commitments: commitments
+                .iter()
+                .map(|&byte| Bytes48::repeat_byte(byte))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_context() {
+        let manifest = Eip4844Manifest {
+            receipts: vec![eip4844_receipt(42, &[7])],
+        };
+        assert_eq!(u64::abi_decode(&manifest.context(), true).unwrap(), 42);
+        let decoded = Eip4844Manifest::from_context(
+            &Bytes48::repeat_byte(7).to_string(),
+            &manifest.context(),
+        )
+        .unwrap();
+        assert_eq!(decoded, manifest);
+
+        let manifest = Eip4844Manifest {
+            receipts: vec![eip4844_receipt(42, &[1, 2]), eip4844_receipt(43, &[3])],
+        };
+        let slots = Vec::<u64>::abi_decode(&manifest.context(), true).unwrap();
+        assert_eq!(slots, vec![42, 42, 43]);
+        let decoded = Eip4844Manifest::from_context(
+            &commitment_list(manifest.commitments()),
+            &manifest.context(),
+        )
+        .unwrap();
+        assert_eq!(decoded, manifest);
+        assert!(Eip4844Manifest::from_context(
+            &Bytes48::repeat_byte(1).to_string(),
+            &manifest.context()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_blobs_for_size() {
+        for size in [0, 11, 22, 23, 31, 126_967, 126_968, 126_976, 300_000] {
+            let data = vec![1u8; size];
+            let sidecar: SidecarBuilder<BlobCoder> = SidecarBuilder::from_slice(&data);
+            let blobs = sidecar.take().len() as u64;
+            assert_eq!(blobs_for_size(size as u64), blobs, "size {size}");
+
+            // A whole number of field elements used to leave no room for the terminator
+            let sidecar: SidecarBuilder<TerminationCoder> = SidecarBuilder::from_slice(&data);
+            let blobs = sidecar.take();
+            assert_eq!(
+                TerminationCoder.decode_all(&blobs),
+                Some(vec![data]),
+                "size {size}"
+            );
+        }
+    }
 }
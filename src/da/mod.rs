@@ -1,23 +1,147 @@
-use std::str::FromStr;
+use std::{future::Future, str::FromStr};
 
+use alloy::primitives::{Bytes, U256};
+use avail::AvailReceipt;
 use base64::Engine;
 use borsh::{BorshDeserialize, BorshSerialize};
+use celestia::CelestiaReceipt;
+use eigenda::EigenDaReceipt;
+use eip4844::Eip4844Manifest;
+use serde::{Deserialize, Serialize};
 
+use crate::{price::Asset, socketio::model::DaLayer};
+
+pub mod avail;
 pub mod celestia;
+pub mod eigenda;
 pub mod eip4844;
+pub mod registry;
 
-#[allow(async_fn_in_trait)]
+// The futures are `Send` so that the registry can box them behind a `DaBackend`. Implementations
+// can still use `async fn`
 pub trait Submitter {
     type Receipt;
 
-    async fn submit(
+    fn submit(
         &self,
         provided_commitment: &str,
         data: BlobData,
-    ) -> eyre::Result<Self::Receipt>;
+    ) -> impl Future<Output = eyre::Result<Self::Receipt>> + Send;
+}
+
+pub trait Estimator {
+    /// The asset the layer charges its fees in
+    const NATIVE_ASSET: Asset;
+
+    /// Estimated cost of posting `size` bytes, in the smallest unit of the layer's native
+    /// asset (wei for EIP-4844 and EigenDA, utia for Celestia, plancks of AVAIL for Avail)
+    fn estimate_cost(&self, size: u64) -> impl Future<Output = eyre::Result<U256>> + Send;
+}
+
+pub trait Prover {
+    type Receipt;
+
+    /// Builds the inclusion proof passed to `Kuda::createCounterChallenge`
+    fn inclusion_proof(
+        &self,
+        receipt: &Self::Receipt,
+    ) -> impl Future<Output = eyre::Result<Bytes>> + Send;
+}
+
+pub trait Verifier {
+    type Receipt;
+
+    /// Whether the blob of `receipt` is available on the DA layer. Only returns `Ok(false)`
+    /// when the blob is certainly missing, so that an unsynced or flaky endpoint is an error
+    fn is_included(
+        &self,
+        receipt: &Self::Receipt,
+    ) -> impl Future<Output = eyre::Result<bool>> + Send;
+
+    /// Reads the blob of a receipt we just got back from the DA layer and checks that it holds
+    /// `data`
+    fn verify_post(
+        &self,
+        receipt: &Self::Receipt,
+        data: &BlobData,
+    ) -> impl Future<Output = eyre::Result<()>> + Send;
+}
+
+pub trait Health {
+    /// Checks that the layer's endpoints are reachable and synced
+    fn health(&self) -> impl Future<Output = eyre::Result<()>> + Send;
+}
+
+/// The receipt of one DA layer, held by its [`DaReceipt`] variant
+pub trait LayerReceipt: Sized {
+    const DA_LAYER: DaLayer;
+
+    fn into_receipt(self) -> DaReceipt;
+
+    /// The receipt held by `receipt`, if it was posted to this layer
+    fn from_receipt(receipt: &DaReceipt) -> Option<&Self>;
+
+    /// ABI-encoded receipt context passed to `Kuda::submitReceipt`
+    fn context(&self) -> Bytes;
+
+    /// Rebuilds a receipt from the `commitment` and `context` of a `Kuda::submitReceipt` call
+    fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self>;
+}
+
+/// Declares [`DaReceipt`] with one variant per DA layer, named after its [`DaLayer`], and
+/// implements [`LayerReceipt`] for the receipt each variant holds. The receipts bring their
+/// own `context` and `from_context`
+macro_rules! da_receipts {
+    ($($layer:ident($receipt:ty)),* $(,)?) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        pub enum DaReceipt {
+            $($layer($receipt),)*
+        }
+
+        impl DaReceipt {
+            pub fn da_layer(&self) -> DaLayer {
+                match self {
+                    $(DaReceipt::$layer(_) => DaLayer::$layer,)*
+                }
+            }
+        }
+
+        $(
+            impl LayerReceipt for $receipt {
+                const DA_LAYER: DaLayer = DaLayer::$layer;
+
+                fn into_receipt(self) -> DaReceipt {
+                    DaReceipt::$layer(self)
+                }
+
+                fn from_receipt(receipt: &DaReceipt) -> Option<&Self> {
+                    match receipt {
+                        DaReceipt::$layer(receipt) => Some(receipt),
+                        _ => None,
+                    }
+                }
+
+                fn context(&self) -> Bytes {
+                    <$receipt>::context(self)
+                }
+
+                fn from_context(commitment: &str, context: &[u8]) -> eyre::Result<Self> {
+                    <$receipt>::from_context(commitment, context)
+                }
+            }
+        )*
+    };
+}
+
+da_receipts! {
+    Celestia(CelestiaReceipt),
+    Eip4844(Eip4844Manifest),
+    Avail(AvailReceipt),
+    EigenDa(EigenDaReceipt),
 }
 
-#[derive(Debug, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, BorshSerialize, BorshDeserialize, PartialEq, Eq)]
 pub struct BlobData {
     pub namespace: Option<celestia::Namespace>,
     pub data: Vec<u8>,
@@ -0,0 +1,252 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy::primitives::{Bytes, U256};
+use futures_util::future::BoxFuture;
+
+use crate::{price::Asset, socketio::model::DaLayer};
+
+use super::{BlobData, DaReceipt, Estimator, Health, LayerReceipt, Prover, Submitter, Verifier};
+
+/// A DA layer tasks can be posted to, so that the rest of the operator deals with every layer the
+/// same way. Backends implement the typed traits of their layer and get registered through
+/// [`DaRegistry::register`], which implements this trait over them
+pub trait DaBackend: Send + Sync {
+    /// The layer this backend posts to
+    fn da_layer(&self) -> DaLayer;
+
+    /// The asset the layer charges its fees in
+    fn native_asset(&self) -> Asset;
+
+    /// Posts `data`, after checking it against the commitment the client computed
+    fn submit<'a>(
+        &'a self,
+        commitment: &'a str,
+        data: BlobData,
+    ) -> BoxFuture<'a, eyre::Result<DaReceipt>>;
+
+    /// See [`super::Verifier::verify_post`]
+    fn verify_post<'a>(
+        &'a self,
+        receipt: &'a DaReceipt,
+        data: &'a BlobData,
+    ) -> BoxFuture<'a, eyre::Result<()>>;
+
+    /// See [`super::Verifier::is_included`]
+    fn is_included<'a>(&'a self, receipt: &'a DaReceipt) -> BoxFuture<'a, eyre::Result<bool>>;
+
+    /// See [`super::Prover::inclusion_proof`]
+    fn inclusion_proof<'a>(&'a self, receipt: &'a DaReceipt) -> BoxFuture<'a, eyre::Result<Bytes>>;
+
+    /// See [`super::Estimator::estimate_cost`]
+    fn estimate_cost(&self, size: u64) -> BoxFuture<'_, eyre::Result<U256>>;
+
+    /// ABI-encoded receipt context passed to `Kuda::submitReceipt`
+    fn context(&self, receipt: &DaReceipt) -> eyre::Result<Bytes>;
+
+    /// Rebuilds a receipt from the `commitment` and `context` of a `Kuda::submitReceipt` call
+    fn receipt_from_context(&self, commitment: &str, context: &[u8]) -> eyre::Result<DaReceipt>;
+
+    /// Checks that the layer's endpoints are reachable and synced
+    fn health(&self) -> BoxFuture<'_, eyre::Result<()>>;
+}
+
+/// Exposes a backend implementing the typed traits of its layer as a [`DaBackend`], handing it
+/// the receipts of its own layer only
+struct Adapter<B>(B);
+
+impl<B, R> DaBackend for Adapter<B>
+where
+    B: Submitter<Receipt = R>
+        + Verifier<Receipt = R>
+        + Prover<Receipt = R>
+        + Estimator
+        + Health
+        + Send
+        + Sync,
+    R: LayerReceipt + Sync,
+{
+    fn da_layer(&self) -> DaLayer {
+        R::DA_LAYER
+    }
+
+    fn native_asset(&self) -> Asset {
+        B::NATIVE_ASSET
+    }
+
+    fn submit<'a>(
+        &'a self,
+        commitment: &'a str,
+        data: BlobData,
+    ) -> BoxFuture<'a, eyre::Result<DaReceipt>> {
+        Box::pin(async move { Ok(self.0.submit(commitment, data).await?.into_receipt()) })
+    }
+
+    fn verify_post<'a>(
+        &'a self,
+        receipt: &'a DaReceipt,
+        data: &'a BlobData,
+    ) -> BoxFuture<'a, eyre::Result<()>> {
+        Box::pin(async move { self.0.verify_post(layer_receipt(receipt)?, data).await })
+    }
+
+    fn is_included<'a>(&'a self, receipt: &'a DaReceipt) -> BoxFuture<'a, eyre::Result<bool>> {
+        Box::pin(async move { self.0.is_included(layer_receipt(receipt)?).await })
+    }
+
+    fn inclusion_proof<'a>(&'a self, receipt: &'a DaReceipt) -> BoxFuture<'a, eyre::Result<Bytes>> {
+        Box::pin(async move { self.0.inclusion_proof(layer_receipt(receipt)?).await })
+    }
+
+    fn estimate_cost(&self, size: u64) -> BoxFuture<'_, eyre::Result<U256>> {
+        Box::pin(self.0.estimate_cost(size))
+    }
+
+    fn context(&self, receipt: &DaReceipt) -> eyre::Result<Bytes> {
+        Ok(layer_receipt::<R>(receipt)?.context())
+    }
+
+    fn receipt_from_context(&self, commitment: &str, context: &[u8]) -> eyre::Result<DaReceipt> {
+        R::from_context(commitment, context).map(LayerReceipt::into_receipt)
+    }
+
+    fn health(&self) -> BoxFuture<'_, eyre::Result<()>> {
+        Box::pin(self.0.health())
+    }
+}
+
+/// The receipt of layer `R` held by `receipt`, failing if it was posted to another layer
+fn layer_receipt<R: LayerReceipt>(receipt: &DaReceipt) -> eyre::Result<&R> {
+    R::from_receipt(receipt).ok_or_else(|| {
+        eyre::eyre!(
+            "{} receipt passed to the {} backend",
+            receipt.da_layer(),
+            R::DA_LAYER
+        )
+    })
+}
+
+/// The DA backends the operator posts to, keyed by layer
+#[derive(Clone, Default)]
+pub struct DaRegistry {
+    backends: HashMap<DaLayer, Arc<dyn DaBackend>>,
+}
+
+impl DaRegistry {
+    /// Registers `backend` for the layer of its receipts, replacing any backend registered for
+    /// it before
+    pub fn register<B, R>(&mut self, backend: B) -> &mut Self
+    where
+        B: Submitter<Receipt = R>
+            + Verifier<Receipt = R>
+            + Prover<Receipt = R>
+            + Estimator
+            + Health
+            + Send
+            + Sync
+            + 'static,
+        R: LayerReceipt + Sync + 'static,
+    {
+        self.backends
+            .insert(R::DA_LAYER, Arc::new(Adapter(backend)));
+        self
+    }
+
+    pub fn get(&self, da_layer: DaLayer) -> eyre::Result<&Arc<dyn DaBackend>> {
+        self.backends
+            .get(&da_layer)
+            .ok_or_else(|| eyre::eyre!("No backend registered for DA layer {da_layer}"))
+    }
+
+    /// The backend of the layer `receipt` was posted to
+    pub fn backend_for(&self, receipt: &DaReceipt) -> eyre::Result<&Arc<dyn DaBackend>> {
+        self.get(receipt.da_layer())
+    }
+
+    pub fn backends(&self) -> impl Iterator<Item = &Arc<dyn DaBackend>> {
+        self.backends.values()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use base64::Engine;
+    use celestia_types::{nmt::Namespace, Commitment};
+
+    use super::*;
+    use crate::da::celestia::CelestiaReceipt;
+
+    /// A Celestia backend whose posts cost one utia per byte
+    pub(crate) struct FakeBackend;
+
+    impl Submitter for FakeBackend {
+        type Receipt = CelestiaReceipt;
+
+        async fn submit(&self, _: &str, _: BlobData) -> eyre::Result<CelestiaReceipt> {
+            Err(eyre::eyre!("not posting"))
+        }
+    }
+
+    impl Verifier for FakeBackend {
+        type Receipt = CelestiaReceipt;
+
+        async fn is_included(&self, _: &CelestiaReceipt) -> eyre::Result<bool> {
+            Ok(true)
+        }
+
+        async fn verify_post(&self, _: &CelestiaReceipt, _: &BlobData) -> eyre::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Prover for FakeBackend {
+        type Receipt = CelestiaReceipt;
+
+        async fn inclusion_proof(&self, _: &CelestiaReceipt) -> eyre::Result<Bytes> {
+            Ok(Bytes::new())
+        }
+    }
+
+    impl Estimator for FakeBackend {
+        const NATIVE_ASSET: Asset = Asset::Tia;
+
+        async fn estimate_cost(&self, size: u64) -> eyre::Result<U256> {
+            Ok(U256::from(size))
+        }
+    }
+
+    impl Health for FakeBackend {
+        async fn health(&self) -> eyre::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn receipt() -> CelestiaReceipt {
+        CelestiaReceipt {
+            height: 1,
+            commitment: Commitment([0; 32]),
+            namespace: Namespace::new_v0(&[1]).unwrap(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_registry() {
+        let mut registry = DaRegistry::default();
+        assert!(registry.get(DaLayer::Celestia).is_err());
+
+        registry.register(FakeBackend);
+        let receipt = receipt().into_receipt();
+        let backend = registry.backend_for(&receipt).unwrap();
+        assert_eq!(backend.da_layer(), DaLayer::Celestia);
+        assert_eq!(backend.native_asset(), Asset::Tia);
+        assert_eq!(backend.estimate_cost(10).await.unwrap(), U256::from(10));
+        assert!(backend.is_included(&receipt).await.unwrap());
+        let commitment = base64::engine::general_purpose::STANDARD.encode([0; 32]);
+        let context = backend.context(&receipt).unwrap();
+        assert_eq!(
+            backend.receipt_from_context(&commitment, &context).unwrap(),
+            receipt
+        );
+        assert!(registry.get(DaLayer::Eip4844).is_err());
+        assert_eq!(registry.backends().count(), 1);
+    }
+}
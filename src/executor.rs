@@ -0,0 +1,192 @@
+use std::{collections::HashMap, future::Future, sync::Arc};
+
+use clap::ValueEnum;
+use metrics::{counter, gauge};
+use tokio::{
+    sync::{Semaphore, TryAcquireError},
+    task::JoinHandle,
+};
+
+use crate::socketio::model::DaLayer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum QueueFullPolicy {
+    /// Drop new tasks while the queue is full
+    Reject,
+    /// Hold new tasks, and with them the Socket.IO event loop, until the queue has room
+    Delay,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutorConfig {
+    /// Maximum number of tasks posted at the same time on each DA layer
+    pub max_concurrent: HashMap<DaLayer, usize>,
+    /// Maximum number of tasks waiting for a slot on each DA layer
+    pub max_queued: usize,
+    pub queue_full_policy: QueueFullPolicy,
+}
+
+struct LayerQueue {
+    /// Held by every task that is queued or running
+    admitted: Arc<Semaphore>,
+    /// Held by every running task
+    running: Arc<Semaphore>,
+    max_admitted: usize,
+    max_running: usize,
+}
+
+/// Runs tasks with a bounded concurrency and a bounded queue per DA layer
+pub struct TaskExecutor {
+    layers: Arc<HashMap<DaLayer, LayerQueue>>,
+    queue_full_policy: QueueFullPolicy,
+}
+
+impl TaskExecutor {
+    pub fn new(config: &ExecutorConfig) -> Self {
+        let layers = config
+            .max_concurrent
+            .iter()
+            .map(|(da_layer, max_running)| {
+                let max_admitted = max_running + config.max_queued;
+                let queue = LayerQueue {
+                    admitted: Arc::new(Semaphore::new(max_admitted)),
+                    running: Arc::new(Semaphore::new(*max_running)),
+                    max_admitted,
+                    max_running: *max_running,
+                };
+                (*da_layer, queue)
+            })
+            .collect();
+        Self {
+            layers: Arc::new(layers),
+            queue_full_policy: config.queue_full_policy,
+        }
+    }
+
+    /// Whether a new task on `da_layer` would be admitted without waiting
+    pub fn has_capacity(&self, da_layer: DaLayer) -> bool {
+        self.layers
+            .get(&da_layer)
+            .is_some_and(|queue| queue.admitted.available_permits() > 0)
+    }
+
+    /// Queues `task` on `da_layer` and spawns it once a slot is free.
+    /// Fails if the queue is full and the policy is to reject
+    pub async fn spawn<F>(&self, da_layer: DaLayer, task: F) -> eyre::Result<JoinHandle<F::Output>>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let queue = self
+            .layers
+            .get(&da_layer)
+            .ok_or_else(|| eyre::eyre!("No task queue for {da_layer}"))?;
+        let admitted = match self.queue_full_policy {
+            QueueFullPolicy::Reject => match queue.admitted.clone().try_acquire_owned() {
+                Ok(permit) => permit,
+                Err(TryAcquireError::NoPermits) => {
+                    counter!("task_queue_rejected", "da_layer" => da_layer.to_string())
+                        .increment(1);
+                    return Err(eyre::eyre!("{da_layer} task queue is full"));
+                }
+                Err(e) => return Err(e.into()),
+            },
+            QueueFullPolicy::Delay => queue.admitted.clone().acquire_owned().await?,
+        };
+        let running = queue.running.clone();
+        let layers = self.layers.clone();
+        report_depth(&layers, da_layer);
+
+        Ok(tokio::spawn(async move {
+            let permit = running.acquire_owned().await;
+            report_depth(&layers, da_layer);
+            let output = task.await;
+            drop(permit);
+            drop(admitted);
+            report_depth(&layers, da_layer);
+            output
+        }))
+    }
+}
+
+fn report_depth(layers: &HashMap<DaLayer, LayerQueue>, da_layer: DaLayer) {
+    let Some(queue) = layers.get(&da_layer) else {
+        return;
+    };
+    let admitted = queue.max_admitted - queue.admitted.available_permits();
+    let running = queue.max_running - queue.running.available_permits();
+    gauge!("task_queue_depth", "da_layer" => da_layer.to_string())
+        .set(admitted.saturating_sub(running) as f64);
+    gauge!("tasks_running", "da_layer" => da_layer.to_string()).set(running as f64);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::sync::Notify;
+
+    use super::*;
+
+    fn executor(max_concurrent: usize, max_queued: usize, policy: QueueFullPolicy) -> TaskExecutor {
+        TaskExecutor::new(&ExecutorConfig {
+            max_concurrent: HashMap::from([(DaLayer::Celestia, max_concurrent)]),
+            max_queued,
+            queue_full_policy: policy,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_executor_rejects_when_full() {
+        let executor = executor(1, 1, QueueFullPolicy::Reject);
+        let release = Arc::new(Notify::new());
+
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let release = release.clone();
+            handles.push(
+                executor
+                    .spawn(DaLayer::Celestia, async move { release.notified().await })
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert!(!executor.has_capacity(DaLayer::Celestia));
+        assert!(executor.spawn(DaLayer::Celestia, async {}).await.is_err());
+        assert!(executor.spawn(DaLayer::Eip4844, async {}).await.is_err());
+
+        for handle in handles {
+            release.notify_one();
+            handle.await.unwrap();
+        }
+        assert!(executor.has_capacity(DaLayer::Celestia));
+    }
+
+    #[tokio::test]
+    async fn test_executor_limits_concurrency() {
+        let executor = executor(2, 8, QueueFullPolicy::Delay);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+
+        let mut handles = Vec::new();
+        for _ in 0..10 {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            handles.push(
+                executor
+                    .spawn(DaLayer::Celestia, async move {
+                        let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                        max_running.fetch_max(now, Ordering::SeqCst);
+                        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+                        running.fetch_sub(1, Ordering::SeqCst);
+                    })
+                    .await
+                    .unwrap(),
+            );
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        assert_eq!(max_running.load(Ordering::SeqCst), 2);
+    }
+}
@@ -1,4 +1,4 @@
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 
 use axum::{
     extract::State,
@@ -6,10 +6,27 @@ use axum::{
     response::{IntoResponse, Response},
     Json,
 };
+use futures_util::future::join_all;
 use serde::{Deserialize, Serialize};
-use tokio::sync::RwLock;
+use url::Url;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{
+    da::registry::DaRegistry,
+    monitor::{MonitoredStatus, SharedStatus},
+    socketio::aggregator::{AggregatorMode, Aggregators},
+};
+
+/// How long a DA backend gets to answer before it is reported unresponsive
+const BACKEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone)]
+pub struct HealthState {
+    pub aggregators: Arc<Aggregators>,
+    pub operator_status: SharedStatus,
+    pub da_registry: Arc<DaRegistry>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Status {
     Ok,
     Warn,
@@ -21,6 +38,25 @@ pub struct HealthCheck {
     status: Status,
     #[serde(skip_serializing_if = "Option::is_none")]
     description: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aggregators: Vec<AggregatorHealth>,
+}
+
+impl HealthCheck {
+    /// Lowers the status to `status` if it is worse, adding `description`
+    fn degrade(&mut self, status: Status, description: String) {
+        self.status = self.status.max(status);
+        self.description = Some(match self.description.take() {
+            Some(existing) => format!("{existing}; {description}"),
+            None => description,
+        });
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregatorHealth {
+    url: Url,
+    connected: bool,
 }
 
 impl IntoResponse for HealthCheck {
@@ -33,18 +69,180 @@ impl IntoResponse for HealthCheck {
     }
 }
 
-#[tracing::instrument]
-pub async fn health_check(State(socket_io_connected): State<Arc<RwLock<bool>>>) -> HealthCheck {
-    let socket_io_connected = socket_io_connected.read().await;
-    if *socket_io_connected {
-        HealthCheck {
-            status: Status::Ok,
-            description: None,
+#[tracing::instrument(skip(state))]
+pub async fn health_check(State(state): State<HealthState>) -> HealthCheck {
+    let mut health = aggregator_health(
+        state.aggregators.mode,
+        state.aggregators.connections().await,
+    );
+    if let Some((status, description)) = operator_health(&*state.operator_status.read().await) {
+        health.degrade(status, description);
+    }
+    let backends = state.da_registry.backends().collect::<Vec<_>>();
+    let results = join_all(
+        backends
+            .iter()
+            .map(|backend| backend_health(backend.health(), BACKEND_TIMEOUT)),
+    )
+    .await;
+    for (backend, result) in backends.iter().zip(results) {
+        if let Some(description) = result {
+            health.degrade(
+                Status::Warn,
+                format!("{} {description}", backend.da_layer()),
+            );
+        }
+    }
+    health
+}
+
+/// Describes what is wrong with a backend, if its health check fails or takes longer than
+/// `timeout`
+async fn backend_health(
+    health: impl Future<Output = eyre::Result<()>>,
+    timeout: Duration,
+) -> Option<String> {
+    match tokio::time::timeout(timeout, health).await {
+        Ok(Ok(())) => None,
+        Ok(Err(e)) => Some(format!("unavailable: {e}")),
+        Err(_) => Some(format!("unresponsive after {timeout:?}")),
+    }
+}
+
+fn operator_health(status: &MonitoredStatus) -> Option<(Status, String)> {
+    match status {
+        MonitoredStatus::Pending => {
+            Some((Status::Warn, "Operator status not polled yet".to_string()))
+        }
+        MonitoredStatus::Error(e) => {
+            Some((Status::Warn, format!("Operator status unavailable: {e}")))
         }
+        MonitoredStatus::Ok(status) if status.jailed => {
+            Some((Status::Fail, "Operator is jailed".to_string()))
+        }
+        MonitoredStatus::Ok(status) if status.bond < status.min_bond => Some((
+            Status::Fail,
+            "Operator bond is below the minimum".to_string(),
+        )),
+        MonitoredStatus::Ok(status)
+            if status.stake.values().all(|vault| vault.amount.is_zero()) =>
+        {
+            Some((Status::Warn, "Operator has no stake".to_string()))
+        }
+        MonitoredStatus::Ok(_) => None,
+    }
+}
+
+fn aggregator_health(mode: AggregatorMode, connections: Vec<(Url, bool)>) -> HealthCheck {
+    let disconnected = connections
+        .iter()
+        .filter(|(_, connected)| !connected)
+        .map(|(url, _)| url.to_string())
+        .collect::<Vec<_>>();
+    let (status, description) = if disconnected.len() == connections.len() {
+        (Status::Fail, Some("Socket IO not connected".to_string()))
     } else {
-        HealthCheck {
-            status: Status::Fail,
-            description: Some("Socket IO not connected".to_string()),
+        match mode {
+            AggregatorMode::All if !disconnected.is_empty() => (
+                Status::Warn,
+                Some(format!(
+                    "Socket IO not connected to {}",
+                    disconnected.join(", ")
+                )),
+            ),
+            AggregatorMode::Failover if !connections[0].1 => (
+                Status::Warn,
+                Some(format!("Failed over from {}", connections[0].0)),
+            ),
+            _ => (Status::Ok, None),
         }
+    };
+    HealthCheck {
+        status,
+        description,
+        aggregators: connections
+            .into_iter()
+            .map(|(url, connected)| AggregatorHealth { url, connected })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, str::FromStr};
+
+    use alloy::primitives::{Address, U256};
+
+    use super::*;
+    use crate::{monitor::OperatorStatus, operator::Vault};
+
+    #[test]
+    fn test_aggregator_health() {
+        let primary = Url::from_str("http://primary").unwrap();
+        let standby = Url::from_str("http://standby").unwrap();
+        let health = |mode, primary_connected, standby_connected| {
+            aggregator_health(
+                mode,
+                vec![
+                    (primary.clone(), primary_connected),
+                    (standby.clone(), standby_connected),
+                ],
+            )
+            .status
+        };
+
+        assert_eq!(health(AggregatorMode::All, true, true), Status::Ok);
+        assert_eq!(health(AggregatorMode::All, false, true), Status::Warn);
+        assert_eq!(health(AggregatorMode::All, false, false), Status::Fail);
+        assert_eq!(health(AggregatorMode::Failover, true, false), Status::Ok);
+        assert_eq!(health(AggregatorMode::Failover, false, true), Status::Warn);
+        assert_eq!(health(AggregatorMode::Failover, false, false), Status::Fail);
+    }
+
+    #[test]
+    fn test_operator_health() {
+        let mut status = OperatorStatus {
+            jailed: false,
+            bond: U256::from(2),
+            min_bond: U256::from(1),
+            stake: HashMap::from([(
+                Address::ZERO,
+                Vault {
+                    symbol: "kETH".to_string(),
+                    name: "Vault".to_string(),
+                    asset: Address::ZERO,
+                    decimals: 18,
+                    amount: U256::from(1),
+                },
+            )]),
+            valuation: Default::default(),
+        };
+        let health = |status: &OperatorStatus| {
+            operator_health(&MonitoredStatus::Ok(status.clone())).map(|(status, _)| status)
+        };
+
+        assert_eq!(health(&status), None);
+        status.bond = U256::ZERO;
+        assert_eq!(health(&status), Some(Status::Fail));
+        status.bond = U256::from(2);
+        status.jailed = true;
+        assert_eq!(health(&status), Some(Status::Fail));
+        status.jailed = false;
+        status.stake.clear();
+        assert_eq!(health(&status), Some(Status::Warn));
+    }
+
+    #[tokio::test]
+    async fn test_backend_health() {
+        let timeout = Duration::from_millis(10);
+        assert_eq!(backend_health(async { Ok(()) }, timeout).await, None);
+        assert_eq!(
+            backend_health(async { Err(eyre::eyre!("connection refused")) }, timeout).await,
+            Some("unavailable: connection refused".to_string())
+        );
+        assert_eq!(
+            backend_health(std::future::pending(), timeout).await,
+            Some("unresponsive after 10ms".to_string())
+        );
     }
 }
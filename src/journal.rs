@@ -0,0 +1,468 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy::primitives::{Address, Bytes, TxHash, U256};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::{Mutex, RwLock},
+};
+use uuid::Uuid;
+
+use crate::{
+    da::DaReceipt,
+    socketio::model::{DaLayer, PostingIntent, TaskResponsibility},
+};
+
+/// Everything `Kuda::submitReceipt` needs about a task, apart from the DA receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskAssignment {
+    pub commitment: String,
+    pub da_layer: DaLayer,
+    pub signature: String,
+    pub submission_time: U256,
+    pub client_address: Address,
+    pub reward_token: Address,
+    pub reward_amount: U256,
+}
+
+impl From<&TaskResponsibility> for TaskAssignment {
+    fn from(task: &TaskResponsibility) -> Self {
+        TaskAssignment {
+            commitment: task.commitment.clone(),
+            da_layer: task.da_layer,
+            signature: task.signature.clone(),
+            submission_time: task.submission_time,
+            client_address: task.client_address,
+            reward_token: task.reward_token,
+            reward_amount: task.reward_amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum TaskEvent {
+    #[serde(rename_all = "camelCase")]
+    IntentReceived {
+        size: u64,
+        client_address: Address,
+        reward_token: Address,
+        reward_amount: U256,
+    },
+    #[serde(rename_all = "camelCase")]
+    InterestSent {
+        da_layer: DaLayer,
+    },
+    Assigned(TaskAssignment),
+    BlobSubmitted {
+        receipt: DaReceipt,
+    },
+    #[serde(rename_all = "camelCase")]
+    ReceiptMined {
+        tx_hash: TxHash,
+    },
+    /// The receipt was found on-chain without us knowing the transaction that submitted it
+    ReceiptFound {
+        receipt: Bytes,
+    },
+    Challenged {
+        challenger: Address,
+    },
+    #[serde(rename_all = "camelCase")]
+    CounterChallenged {
+        tx_hash: TxHash,
+    },
+    /// We challenged the receipt another operator submitted for the task
+    #[serde(rename_all = "camelCase")]
+    ChallengeOpened {
+        operator: Address,
+        tx_hash: TxHash,
+    },
+    Settled,
+    Failed {
+        error: String,
+    },
+}
+
+impl From<&PostingIntent> for TaskEvent {
+    fn from(intent: &PostingIntent) -> Self {
+        TaskEvent::IntentReceived {
+            size: intent.size,
+            client_address: intent.client_address,
+            reward_token: intent.reward_token,
+            reward_amount: intent.reward_amount,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskStage {
+    #[default]
+    IntentReceived,
+    InterestSent,
+    Assigned,
+    BlobSubmitted,
+    ReceiptMined,
+    Challenged,
+    CounterChallenged,
+    ChallengeOpened,
+    Settled,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub task_id: Uuid,
+    pub timestamp: u64,
+    pub event: TaskEvent,
+}
+
+/// The state of a task, rebuilt by replaying its journal entries
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskRecord {
+    pub stage: TaskStage,
+    pub assignment: Option<TaskAssignment>,
+    pub receipt: Option<DaReceipt>,
+    pub receipt_tx_hash: Option<TxHash>,
+    pub on_chain_receipt: Option<Bytes>,
+    pub counter_challenge_tx_hash: Option<TxHash>,
+    pub challenge_tx_hash: Option<TxHash>,
+}
+
+impl TaskRecord {
+    fn apply(&mut self, event: &TaskEvent) {
+        self.stage = match event {
+            TaskEvent::IntentReceived { .. } => TaskStage::IntentReceived,
+            TaskEvent::InterestSent { .. } => TaskStage::InterestSent,
+            TaskEvent::Assigned(assignment) => {
+                self.assignment = Some(assignment.clone());
+                TaskStage::Assigned
+            }
+            TaskEvent::BlobSubmitted { receipt } => {
+                self.receipt = Some(receipt.clone());
+                TaskStage::BlobSubmitted
+            }
+            TaskEvent::ReceiptMined { tx_hash } => {
+                self.receipt_tx_hash = Some(*tx_hash);
+                TaskStage::ReceiptMined
+            }
+            TaskEvent::ReceiptFound { receipt } => {
+                self.on_chain_receipt = Some(receipt.clone());
+                TaskStage::ReceiptMined
+            }
+            TaskEvent::Challenged { .. } => TaskStage::Challenged,
+            TaskEvent::CounterChallenged { tx_hash } => {
+                self.counter_challenge_tx_hash = Some(*tx_hash);
+                TaskStage::CounterChallenged
+            }
+            TaskEvent::ChallengeOpened { tx_hash, .. } => {
+                self.challenge_tx_hash = Some(*tx_hash);
+                TaskStage::ChallengeOpened
+            }
+            TaskEvent::Settled => TaskStage::Settled,
+            TaskEvent::Failed { .. } => TaskStage::Failed,
+        };
+    }
+
+    /// Whether the receipt is known to be on-chain
+    pub fn is_receipt_submitted(&self) -> bool {
+        self.receipt_tx_hash.is_some() || self.on_chain_receipt.is_some()
+    }
+
+    /// Whether the blob was posted to the DA layer but the receipt never made it on-chain
+    pub fn is_receipt_pending(&self) -> bool {
+        self.assignment.is_some() && self.receipt.is_some() && !self.is_receipt_submitted()
+    }
+}
+
+/// The block a log watcher resumes from, kept in a file so that the events emitted while the
+/// operator was down are not missed
+pub struct BlockCursor {
+    path: PathBuf,
+}
+
+impl BlockCursor {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// The saved block, if any
+    pub async fn load(&self) -> eyre::Result<Option<u64>> {
+        if !tokio::fs::try_exists(&self.path).await? {
+            return Ok(None);
+        }
+        let contents = tokio::fs::read_to_string(&self.path).await?;
+        Ok(Some(contents.trim().parse()?))
+    }
+
+    /// Durably replaces the saved block with `block`
+    pub async fn save(&self, block: u64) -> eyre::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = File::create(&tmp_path).await?;
+        file.write_all(block.to_string().as_bytes()).await?;
+        file.sync_data().await?;
+        tokio::fs::rename(&tmp_path, &self.path).await?;
+        Ok(())
+    }
+}
+
+/// Append-only, newline-delimited JSON journal of the lifecycle of every task we handle
+pub struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+    tasks: RwLock<HashMap<Uuid, TaskRecord>>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, creating it if needed, and replays it to rebuild task state
+    pub async fn open(path: &Path) -> eyre::Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        let mut tasks: HashMap<Uuid, TaskRecord> = HashMap::new();
+        let mut terminated = true;
+        if tokio::fs::try_exists(path).await? {
+            let contents = tokio::fs::read_to_string(path).await?;
+            terminated = contents.is_empty() || contents.ends_with('\n');
+            for (line_number, line) in contents.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                // A crash mid-write can leave a truncated last line behind
+                match serde_json::from_str::<JournalEntry>(line) {
+                    Ok(entry) => tasks.entry(entry.task_id).or_default().apply(&entry.event),
+                    Err(e) => tracing::warn!(
+                        "Skipping malformed journal entry at {}:{}: {e}",
+                        path.display(),
+                        line_number + 1
+                    ),
+                }
+            }
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        if !terminated {
+            // Keep new entries off the truncated line
+            file.write_all(b"\n").await?;
+        }
+
+        tracing::info!(
+            "Opened task journal at {} with {} tasks",
+            path.display(),
+            tasks.len()
+        );
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(file),
+            tasks: RwLock::new(tasks),
+        })
+    }
+
+    /// The cursor of the log watcher `name`, kept next to the journal
+    pub fn cursor(&self, name: &str) -> BlockCursor {
+        BlockCursor::new(self.path.with_extension(format!("{name}.cursor")))
+    }
+
+    /// Durably appends `event` for `task_id` and applies it to the task state
+    pub async fn record(&self, task_id: Uuid, event: TaskEvent) -> eyre::Result<()> {
+        let entry = JournalEntry {
+            task_id,
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            event,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+
+        {
+            let mut file = self.file.lock().await;
+            file.write_all(&line).await?;
+            file.sync_data().await?;
+        }
+
+        self.tasks
+            .write()
+            .await
+            .entry(task_id)
+            .or_default()
+            .apply(&entry.event);
+        Ok(())
+    }
+
+    /// Records `event`, logging instead of failing so that journaling never aborts a task
+    pub async fn record_or_log(&self, task_id: Uuid, event: TaskEvent) {
+        if let Err(e) = self.record(task_id, event).await {
+            tracing::error!("Failed to record journal entry for task {task_id}: {e:?}");
+        }
+    }
+
+    pub async fn task(&self, task_id: &Uuid) -> Option<TaskRecord> {
+        self.tasks.read().await.get(task_id).cloned()
+    }
+
+    pub async fn receipt(&self, task_id: &Uuid) -> Option<DaReceipt> {
+        self.tasks
+            .read()
+            .await
+            .get(task_id)
+            .and_then(|task| task.receipt.clone())
+    }
+
+    /// Challenges we are part of, as the challenged operator or as the challenger, that are
+    /// not settled yet
+    pub async fn open_challenges(&self) -> Vec<(Uuid, TaskRecord)> {
+        self.tasks
+            .read()
+            .await
+            .iter()
+            .filter(|(_, task)| {
+                matches!(
+                    task.stage,
+                    TaskStage::Challenged
+                        | TaskStage::CounterChallenged
+                        | TaskStage::ChallengeOpened
+                )
+            })
+            .map(|(task_id, task)| (*task_id, task.clone()))
+            .collect()
+    }
+
+    /// Tasks whose blob was posted but whose receipt was never mined
+    pub async fn pending_receipts(&self) -> Vec<(Uuid, TaskRecord)> {
+        self.tasks
+            .read()
+            .await
+            .iter()
+            .filter(|(_, task)| task.is_receipt_pending())
+            .map(|(task_id, task)| (*task_id, task.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::da::eip4844::{Eip4844Manifest, Eip4844Receipt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_journal_replay() {
+        let path = std::env::temp_dir().join(format!("kuda-journal-{}.jsonl", Uuid::new_v4()));
+        let task_id = Uuid::new_v4();
+        let assignment = TaskAssignment {
+            commitment: "0x00".to_string(),
+            da_layer: DaLayer::Eip4844,
+            signature: "0x00".to_string(),
+            submission_time: U256::from(1),
+            client_address: Address::ZERO,
+            reward_token: Address::ZERO,
+            reward_amount: U256::from(100),
+        };
+        let receipt = DaReceipt::Eip4844(Eip4844Manifest {
+            receipts: vec![Eip4844Receipt {
+                beacon_block_slot: 42,
+                commitments: vec![Default::default()],
+            }],
+        });
+
+        let journal = Journal::open(&path).await.unwrap();
+        journal
+            .record(task_id, TaskEvent::Assigned(assignment.clone()))
+            .await
+            .unwrap();
+        journal
+            .record(task_id, TaskEvent::BlobSubmitted { receipt })
+            .await
+            .unwrap();
+        drop(journal);
+
+        let journal = Journal::open(&path).await.unwrap();
+        let pending = journal.pending_receipts().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0, task_id);
+        assert_eq!(pending[0].1.stage, TaskStage::BlobSubmitted);
+        assert_eq!(pending[0].1.assignment, Some(assignment.clone()));
+
+        journal
+            .record(
+                task_id,
+                TaskEvent::ReceiptMined {
+                    tx_hash: TxHash::ZERO,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(journal.pending_receipts().await.is_empty());
+
+        let other_task_id = Uuid::new_v4();
+        journal
+            .record(other_task_id, TaskEvent::Assigned(assignment))
+            .await
+            .unwrap();
+        journal
+            .record(
+                other_task_id,
+                TaskEvent::ReceiptFound {
+                    receipt: Bytes::from_static(&[1]),
+                },
+            )
+            .await
+            .unwrap();
+        let task = journal.task(&other_task_id).await.unwrap();
+        assert_eq!(task.stage, TaskStage::ReceiptMined);
+        assert!(task.is_receipt_submitted());
+
+        let challenged_task_id = Uuid::new_v4();
+        journal
+            .record(
+                challenged_task_id,
+                TaskEvent::ChallengeOpened {
+                    operator: Address::repeat_byte(1),
+                    tx_hash: TxHash::ZERO,
+                },
+            )
+            .await
+            .unwrap();
+        let open_challenges = journal.open_challenges().await;
+        assert_eq!(open_challenges.len(), 1);
+        assert_eq!(open_challenges[0].0, challenged_task_id);
+        journal
+            .record(challenged_task_id, TaskEvent::Settled)
+            .await
+            .unwrap();
+        assert!(journal.open_challenges().await.is_empty());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_block_cursor() {
+        let path = std::env::temp_dir().join(format!("kuda-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Journal::open(&path).await.unwrap();
+        let cursor = journal.cursor("test");
+        assert_eq!(cursor.load().await.unwrap(), None);
+        cursor.save(42).await.unwrap();
+        cursor.save(43).await.unwrap();
+        assert_eq!(journal.cursor("test").load().await.unwrap(), Some(43));
+        assert_eq!(journal.cursor("other").load().await.unwrap(), None);
+
+        tokio::fs::remove_file(path.with_extension("test.cursor"))
+            .await
+            .unwrap();
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
@@ -1,18 +1,30 @@
-use std::sync::Arc;
-
 use axum::{routing::get, Router};
 use clap::ValueEnum;
+use health::HealthState;
 use serde::Deserialize;
-use tokio::sync::RwLock;
 
+pub mod bidding;
+pub mod challenge;
 pub mod contracts;
+pub mod cost;
 pub mod da;
+pub mod executor;
 pub mod health;
+pub mod journal;
 pub mod kms;
+pub mod lifecycle;
+pub mod monitor;
 pub mod operator;
+pub mod price;
 pub mod register;
+pub mod retry;
 pub mod run;
+pub mod settlement;
+pub mod slashing;
 pub mod socketio;
+pub mod staking;
+pub mod valuation;
+pub mod watchtower;
 
 #[derive(Deserialize, Clone, Copy, Debug, ValueEnum)]
 #[serde(rename_all = "snake_case")]
@@ -21,8 +33,8 @@ pub enum Kms {
     Aws,
 }
 
-pub fn routes(socket_io_connected: Arc<RwLock<bool>>) -> Router {
+pub fn routes(health_state: HealthState) -> Router {
     Router::new()
         .route("/health", get(health::health_check))
-        .with_state(socket_io_connected)
+        .with_state(health_state)
 }
@@ -0,0 +1,196 @@
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{utils::format_units, Address, B256, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use clap::ValueEnum;
+use eyre::OptionExt;
+use serde::Serialize;
+
+use crate::{
+    contracts::kuda::Kuda::KudaErrors, monitor::OperatorStatus, operator::Operator,
+    price::PriceSource,
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusReport {
+    pub operator: Address,
+    pub registered: bool,
+    pub active_vaults: Vec<Address>,
+    #[serde(flatten)]
+    pub status: OperatorStatus,
+}
+
+pub async fn status<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    price_source: &PriceSource,
+    format: OutputFormat,
+) -> eyre::Result<()> {
+    let report = StatusReport {
+        operator: operator.operator_address,
+        registered: operator.is_registered().await?,
+        active_vaults: operator.active_vaults().await?,
+        status: OperatorStatus::fetch(operator, price_source).await?,
+    };
+
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        OutputFormat::Table => {
+            let yes_no = |value: bool| if value { "yes" } else { "no" };
+            println!("{:<16}{}", "Operator", report.operator);
+            println!("{:<16}{}", "Registered", yes_no(report.registered));
+            println!("{:<16}{}", "Jailed", yes_no(report.status.jailed));
+            println!(
+                "{:<16}{} ETH (minimum {} ETH)",
+                "Bond",
+                format_units(report.status.bond, "ether")?,
+                format_units(report.status.min_bond, "ether")?
+            );
+            println!("{:<16}{}", "Active vaults", report.active_vaults.len());
+            for vault in &report.active_vaults {
+                println!("  {vault}");
+            }
+            println!("{:<16}{}", "Staked vaults", report.status.stake.len());
+            let valuation = &report.status.valuation;
+            for (address, vault) in &report.status.stake {
+                let usd = match valuation.vaults.get(address) {
+                    Some(value) => format!("${:.2}", value.usd),
+                    None => "no price".to_string(),
+                };
+                println!(
+                    "  {address}  {} {} ({}, {usd})",
+                    format_units(vault.amount, vault.decimals)?,
+                    vault.symbol,
+                    vault.name
+                );
+            }
+            let total_eth = match valuation.total_eth {
+                Some(eth) => format!("{eth:.4} ETH"),
+                None => "no ETH price".to_string(),
+            };
+            println!(
+                "{:<16}${:.2} ({total_eth})",
+                "Total stake", valuation.total_usd
+            );
+            if !valuation.unpriced_vaults.is_empty() {
+                println!(
+                    "  excluding {} vault(s) without a price",
+                    valuation.unpriced_vaults.len()
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn unregister<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+) -> eyre::Result<()> {
+    if !operator.is_registered().await? {
+        println!("Operator not registered with KUDA");
+        return Ok(());
+    }
+    let tx_hash = operator.unregister().await?;
+    println!("Operator unregistered from KUDA with tx hash: {tx_hash}");
+    Ok(())
+}
+
+/// Submits `amount` wei of bond, or the difference to the minimum bond if not set
+pub async fn submit_bond<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    amount: Option<U256>,
+) -> eyre::Result<()> {
+    let amount = match amount {
+        Some(amount) => amount,
+        None => {
+            let bond = operator.bond().await?;
+            let min_bond = operator.min_bond().await?;
+            if bond >= min_bond {
+                println!(
+                    "Operator bond = {} ETH already covers the minimum operator bond = {} ETH",
+                    format_units(bond, "ether")?,
+                    format_units(min_bond, "ether")?
+                );
+                return Ok(());
+            }
+            min_bond - bond
+        }
+    };
+    let tx_hash = operator.submit_operator_bond(amount).await?;
+    println!("Operator bond submitted with tx hash: {tx_hash}");
+    Ok(())
+}
+
+pub async fn start_withdraw<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    token: Address,
+) -> eyre::Result<()> {
+    let delay = operator
+        .kuda_instance
+        .FULL_WITHDRAW_DELAY()
+        .call()
+        .await?
+        ._0;
+    let (withdraw_hash, tx_hash, block_number) = operator.start_full_withdraw(token).await?;
+    let started_at = operator
+        .provider
+        .get_block_by_number(BlockNumberOrTag::Number(block_number), false)
+        .await?
+        .ok_or_eyre("Could not get withdraw block")?
+        .header
+        .timestamp;
+
+    println!("Full withdraw of {token} started with tx hash: {tx_hash}");
+    println!("Withdraw hash: {withdraw_hash}");
+    println!(
+        "Run `kuda-operator withdraw finish {withdraw_hash}` after {} (unix time), once the {delay} second withdraw delay has passed",
+        U256::from(started_at) + delay
+    );
+    Ok(())
+}
+
+pub async fn finish_withdraw<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    withdraw_hash: B256,
+) -> eyre::Result<()> {
+    // Simulate first so that an early finish is explained instead of reverting on-chain
+    if let Err(alloy::contract::Error::TransportError(e)) = operator
+        .kuda_instance
+        .finishFullWithdraw(withdraw_hash)
+        .call()
+        .await
+    {
+        match e
+            .as_error_resp()
+            .and_then(|payload| payload.as_decoded_error::<KudaErrors>(true))
+        {
+            Some(KudaErrors::WithdrawDelayNotPassed(_)) => {
+                let delay = operator
+                    .kuda_instance
+                    .FULL_WITHDRAW_DELAY()
+                    .call()
+                    .await?
+                    ._0;
+                return Err(eyre::eyre!(
+                    "The {delay} second withdraw delay has not passed yet"
+                ));
+            }
+            Some(KudaErrors::WithdrawNotFound(_)) => {
+                return Err(eyre::eyre!("No withdraw found for {withdraw_hash}"));
+            }
+            _ => return Err(e.into()),
+        }
+    }
+
+    let tx_hash = operator.finish_full_withdraw(withdraw_hash).await?;
+    println!("Full withdraw finished with tx hash: {tx_hash}");
+    Ok(())
+}
@@ -1,21 +1,34 @@
-use std::{net::IpAddr, path::PathBuf, sync::Arc};
+use std::{collections::HashMap, net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use alloy::{
     network::{EthereumWallet, TxSigner},
-    primitives::Address,
+    primitives::{utils::parse_ether, Address, B256, U256},
     providers::ProviderBuilder,
 };
 use clap::{
     builder::{styling::AnsiColor, Styles},
-    Parser, Subcommand,
+    Args, Parser, Subcommand,
 };
 use kuda_operator::{
+    bidding::{BiddingConfig, DefaultBiddingPolicy},
     contracts::kuda::Kuda::{self},
-    da::{celestia::CelestiaClient, eip4844::Eip4844Client},
+    da::{
+        avail::AvailClient,
+        celestia::CelestiaClient,
+        eigenda::EigenDaClient,
+        eip4844::{Eip4844Client, ReplacementConfig},
+        registry::DaRegistry,
+    },
+    executor::{ExecutorConfig, QueueFullPolicy},
+    lifecycle::{self, OutputFormat},
+    monitor::BondTopUp,
     operator::Operator,
+    price::{AssetFeed, AssetPrice, PriceSource},
     register::{register, RegisterConfig},
+    retry::RetryConfig,
     run::{run, RunConfig},
-    Kms,
+    socketio::{aggregator::AggregatorMode, model::DaLayer, signature::AggregatorSigner},
+    staking, Kms,
 };
 use url::Url;
 
@@ -25,12 +38,73 @@ const CLAP_STYLING: Styles = Styles::styled()
     .literal(AnsiColor::Green.on_default())
     .placeholder(AnsiColor::Green.on_default());
 
+/// Where USD prices come from, used to convert DA costs into reward tokens and to value stake.
+/// `<ASSET>` is `eth`, `tia`, `avail` or a token address. Live sources are tried before static
+/// prices
+#[derive(Args)]
+struct PriceArgs {
+    /// Static USD prices, as `<ASSET>=<PRICE>`
+    #[arg(long, env, value_delimiter = ',')]
+    usd_prices: Vec<AssetPrice>,
+
+    /// Chainlink-style USD price feeds, as `<ASSET>=<FEED_ADDRESS>`
+    #[arg(long, env, value_delimiter = ',')]
+    price_feeds: Vec<AssetFeed>,
+
+    /// RPC of the chain the price feeds are on. Defaults to the KUDA RPC
+    #[arg(long, env)]
+    price_feed_rpc_url: Option<Url>,
+
+    /// Maximum age in seconds of a price feed answer, past which the next source is used
+    #[arg(long, env, default_value = "3600")]
+    price_feed_max_age: u64,
+
+    /// HTTP price source answering `GET <URL>/<ASSET>` with `{"usd": <PRICE>}`
+    #[arg(long, env)]
+    price_url: Option<Url>,
+}
+
+impl PriceArgs {
+    fn price_source(self, kuda_rpc_url: &Url) -> PriceSource {
+        let mut sources = Vec::new();
+        if let Some(price_url) = self.price_url {
+            sources.push(PriceSource::from_url(price_url));
+        }
+        if !self.price_feeds.is_empty() {
+            let rpc_url = self
+                .price_feed_rpc_url
+                .unwrap_or_else(|| kuda_rpc_url.clone());
+            sources.push(PriceSource::from_feeds(
+                rpc_url,
+                &self.price_feeds,
+                Duration::from_secs(self.price_feed_max_age),
+            ));
+        }
+        sources.push(PriceSource::from_prices(&self.usd_prices));
+        if sources.len() == 1 {
+            sources.remove(0)
+        } else {
+            PriceSource::Fallback(sources)
+        }
+    }
+}
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum KudaOperatorCommand {
     Run {
-        #[arg(short, long, env)]
-        aggregator_url: Url,
+        /// Aggregators to listen to, in order of preference
+        #[arg(short, long, env, value_delimiter = ',', required = true)]
+        aggregator_url: Vec<Url>,
+
+        /// Whether to connect to all aggregators at once or fail over between them in order
+        #[arg(long, env, default_value = "all")]
+        aggregator_mode: AggregatorMode,
+
+        /// Address the aggregator signs task assignments with. If not set, signatures are
+        /// checked by simulating `submitReceipt` on the Kuda contract
+        #[arg(long, env)]
+        aggregator_address: Option<Address>,
 
         #[arg(long, env)]
         celestia_rpc_url: Url,
@@ -38,6 +112,10 @@ enum KudaOperatorCommand {
         #[arg(long, env)]
         celestia_auth_token: Option<String>,
 
+        /// Gas price in utia for Celestia submissions. Defaults to the node's minimum gas price
+        #[arg(long, env)]
+        celestia_gas_price: Option<f64>,
+
         #[arg(long, env, required_if_eq("kms", "aws"))]
         aws_eip4844_key_id: Option<String>,
 
@@ -56,6 +134,168 @@ enum KudaOperatorCommand {
         #[arg(long, env)]
         eip4844_beacon_url: Url,
 
+        /// Seconds to wait for a blob transaction to be included before replacing it
+        #[arg(long, env, default_value = "120")]
+        eip4844_inclusion_timeout: u64,
+
+        /// Maximum number of times a blob transaction is replaced with bumped fees
+        #[arg(long, env, default_value = "3")]
+        eip4844_max_replacements: usize,
+
+        /// Post payloads needing more than one blob, over several transactions if needed.
+        /// Experimental: the Kuda EIP-4844 verifier can't prove their receipts yet
+        #[arg(long, env)]
+        eip4844_multi_blob_payloads: bool,
+
+        /// Substrate RPC URL of an Avail node. Avail tasks are only bid on when set
+        #[arg(long, env, requires = "avail_seed", requires = "experimental_avail")]
+        avail_rpc_url: Option<Url>,
+
+        /// Bid on and post Avail tasks. Experimental: the Kuda contract has no Avail verifier
+        /// yet, so their receipts can't be submitted nor defended against a challenge
+        #[arg(long, env)]
+        experimental_avail: bool,
+
+        /// Hex seed of the sr25519 key signing Avail extrinsics
+        #[arg(long, env)]
+        avail_seed: Option<B256>,
+
+        /// Application id Avail data is submitted under
+        #[arg(long, env, default_value = "0")]
+        avail_app_id: u32,
+
+        /// Seconds to wait for an Avail extrinsic to be finalized
+        #[arg(long, env, default_value = "120")]
+        avail_inclusion_timeout: u64,
+
+        /// gRPC URL of an EigenDA disperser. EigenDA tasks are only bid on when set
+        #[arg(long, env, requires = "experimental_eigenda")]
+        eigenda_disperser_url: Option<Url>,
+
+        /// Bid on and disperse EigenDA tasks. Experimental: the Kuda contract has no EigenDA
+        /// verifier yet, so their receipts can't be submitted nor defended against a challenge
+        #[arg(long, env)]
+        experimental_eigenda: bool,
+
+        /// Seconds between two polls of the status of a dispersed blob
+        #[arg(long, env, default_value = "10")]
+        eigenda_poll_interval: u64,
+
+        /// Seconds to wait for a dispersed blob to be confirmed
+        #[arg(long, env, default_value = "1800")]
+        eigenda_confirmation_timeout: u64,
+
+        /// Wei accounted for every 32-byte symbol dispersed to EigenDA, used to estimate costs
+        #[arg(long, env, default_value = "0")]
+        eigenda_price_per_symbol: U256,
+
+        /// Seconds before the first retry of a failed DA submission, doubling on every retry
+        #[arg(long, env, default_value = "2")]
+        retry_initial_backoff: u64,
+
+        /// Maximum seconds between retries of a failed DA submission
+        #[arg(long, env, default_value = "60")]
+        retry_max_backoff: u64,
+
+        /// Seconds after a task's submission time during which failed DA submissions are retried
+        #[arg(long, env, default_value = "600")]
+        retry_deadline: u64,
+
+        /// Read every posted blob back from its DA layer before submitting its receipt, posting
+        /// it again if it does not match
+        #[arg(long, env)]
+        verify_posts: bool,
+
+        /// Maximum number of tasks posted to Celestia at the same time
+        #[arg(long, env, default_value = "4")]
+        celestia_max_concurrent_tasks: usize,
+
+        /// Maximum number of tasks posted as EIP-4844 blobs at the same time
+        #[arg(long, env, default_value = "1")]
+        eip4844_max_concurrent_tasks: usize,
+
+        /// Maximum number of tasks posted to Avail at the same time
+        #[arg(long, env, default_value = "4")]
+        avail_max_concurrent_tasks: usize,
+
+        /// Maximum number of tasks dispersed to EigenDA at the same time
+        #[arg(long, env, default_value = "4")]
+        eigenda_max_concurrent_tasks: usize,
+
+        /// Maximum number of tasks waiting for a slot on each DA layer
+        #[arg(long, env, default_value = "16")]
+        max_queued_tasks: usize,
+
+        /// What to do with new tasks when a DA layer's queue is full
+        #[arg(long, env, default_value = "reject")]
+        queue_full_policy: QueueFullPolicy,
+
+        /// Interval in seconds between polls for challenges raised against us and challenges to finish
+        #[arg(long, env, default_value = "12")]
+        challenge_poll_interval: u64,
+
+        /// Check the receipts other operators submit and challenge the ones whose blob is missing,
+        /// posting the challenge bond from the operator account
+        #[arg(long, env)]
+        watchtower: bool,
+
+        /// Interval in seconds between polls for receipts submitted by other operators
+        #[arg(long, env, default_value = "12")]
+        watchtower_poll_interval: u64,
+
+        /// Interval in seconds between polls for slashing of the operator's vaults
+        #[arg(long, env, default_value = "12")]
+        slashing_poll_interval: u64,
+
+        /// Path of the log of slashing events concerning the operator's vaults
+        #[arg(long, env, default_value = "slashing.jsonl")]
+        slashing_log_path: PathBuf,
+
+        /// Webhook receiving a `{"text": <MESSAGE>}` POST for every slashing event
+        #[arg(long, env)]
+        slashing_alert_webhook: Option<Url>,
+
+        /// Interval in seconds between checks of the operator's jail state, bond and stake
+        #[arg(long, env, default_value = "60")]
+        operator_monitor_interval: u64,
+
+        /// If set, the operator bond is topped back up to this amount in wei whenever it drops
+        /// below it
+        #[arg(long, env)]
+        bond_top_up_threshold: Option<U256>,
+
+        /// Minimum reward, in reward token units, per byte of data
+        #[arg(long, env, default_value = "0")]
+        min_reward_per_byte: U256,
+
+        /// Maximum size in bytes of the data we are willing to post
+        #[arg(long, env)]
+        max_size: Option<u64>,
+
+        /// Only bid on intents rewarding in these tokens
+        #[arg(long, env, value_delimiter = ',')]
+        token_allowlist: Vec<Address>,
+
+        /// Never bid on intents rewarding in these tokens
+        #[arg(long, env, value_delimiter = ',')]
+        token_denylist: Vec<Address>,
+
+        /// DA layers to post to, most preferred first
+        #[arg(long, env, value_delimiter = ',')]
+        da_layer_preference: Vec<DaLayer>,
+
+        /// Bid on DA layers whose cost could not be estimated, e.g. for lack of a price, instead
+        /// of skipping them
+        #[arg(long, env)]
+        bid_without_cost_estimate: bool,
+
+        #[command(flatten)]
+        prices: PriceArgs,
+
+        /// Path of the task journal used for crash recovery and auditing
+        #[arg(long, env, default_value = "journal.jsonl")]
+        journal_path: PathBuf,
+
         #[arg(long, env)]
         otel_exporter_otlp_endpoint: Option<Url>,
 
@@ -67,6 +307,91 @@ enum KudaOperatorCommand {
     },
 
     Register,
+
+    /// Print the registration, jail state, bond, vaults and stake of the operator
+    Status {
+        #[arg(long, default_value = "table")]
+        format: OutputFormat,
+
+        #[command(flatten)]
+        prices: PriceArgs,
+    },
+
+    /// Unregister the operator from KUDA
+    Unregister,
+
+    /// Submit operator bond
+    Bond {
+        /// Amount in ETH. Defaults to what is missing to reach the minimum operator bond
+        #[arg(long, value_parser = parse_ether)]
+        amount: Option<U256>,
+    },
+
+    /// Create, fund and stake vaults
+    Vault {
+        /// File tracking the stake updates waiting to be finalized
+        #[arg(long, env, default_value = "stake-updates.json")]
+        stake_updates_path: PathBuf,
+
+        #[command(subcommand)]
+        command: VaultCommand,
+    },
+
+    /// Withdraw the full KUDA balance of a token, in two steps separated by `FULL_WITHDRAW_DELAY`
+    Withdraw {
+        #[command(subcommand)]
+        command: WithdrawCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum VaultCommand {
+    /// Deploy a vault for an asset
+    Create {
+        #[arg(long)]
+        asset: Address,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        symbol: String,
+        /// Vault implementation. Defaults to Core's standard implementation
+        #[arg(long, default_value = "0x0000000000000000000000000000000000000000")]
+        vault_impl: Address,
+    },
+    /// Deposit to a vault
+    Deposit {
+        #[arg(long)]
+        vault: Address,
+        /// Amount in whole units of the vault's asset
+        #[arg(long)]
+        amount: String,
+    },
+    /// Request staking a vault to KUDA
+    Stake {
+        #[arg(long)]
+        vault: Address,
+    },
+    /// Request unstaking a vault from KUDA
+    Unstake {
+        #[arg(long)]
+        vault: Address,
+    },
+    /// Finalize the pending stake update of a vault, or of all vaults
+    Finalize {
+        #[arg(long)]
+        vault: Option<Address>,
+    },
+}
+
+#[derive(Subcommand)]
+enum WithdrawCommand {
+    /// Start the withdrawal and print its withdraw hash
+    Start {
+        #[arg(long)]
+        token: Address,
+    },
+    /// Finish a withdrawal once its delay has passed
+    Finish { withdraw_hash: B256 },
 }
 
 #[derive(Parser)]
@@ -162,6 +487,7 @@ async fn main() -> eyre::Result<()> {
         }
     };
 
+    let kuda_rpc_url = cli.kuda_rpc_url.clone();
     let operator_signer = kuda_operator::kms::get_signer(operator_kms).await?;
     let operator_address = operator_signer.address();
     let operator_wallet = EthereumWallet::from(operator_signer.clone());
@@ -169,7 +495,7 @@ async fn main() -> eyre::Result<()> {
     let provider = ProviderBuilder::new()
         .with_recommended_fillers()
         .wallet(operator_wallet)
-        .on_http(cli.kuda_rpc_url);
+        .on_http(kuda_rpc_url.clone());
 
     if cli.kuda_contract_address == Address::default() {
         tracing::error!("KUDA contract address must be set");
@@ -191,14 +517,56 @@ async fn main() -> eyre::Result<()> {
     match cli.command {
         KudaOperatorCommand::Run {
             aggregator_url,
+            aggregator_mode,
+            aggregator_address,
             celestia_rpc_url,
             celestia_auth_token,
+            celestia_gas_price,
             aws_eip4844_key_id,
             eip4844_keystore_path,
             eip4844_keystore_password,
             eip4844_to_address,
             eip4844_rpc_url,
             eip4844_beacon_url,
+            eip4844_inclusion_timeout,
+            eip4844_max_replacements,
+            eip4844_multi_blob_payloads,
+            avail_rpc_url,
+            avail_seed,
+            experimental_avail,
+            avail_app_id,
+            avail_inclusion_timeout,
+            eigenda_disperser_url,
+            experimental_eigenda,
+            eigenda_poll_interval,
+            eigenda_confirmation_timeout,
+            eigenda_price_per_symbol,
+            retry_initial_backoff,
+            retry_max_backoff,
+            retry_deadline,
+            verify_posts,
+            celestia_max_concurrent_tasks,
+            eip4844_max_concurrent_tasks,
+            avail_max_concurrent_tasks,
+            eigenda_max_concurrent_tasks,
+            max_queued_tasks,
+            queue_full_policy,
+            challenge_poll_interval,
+            watchtower,
+            watchtower_poll_interval,
+            slashing_poll_interval,
+            slashing_log_path,
+            slashing_alert_webhook,
+            operator_monitor_interval,
+            bond_top_up_threshold,
+            journal_path,
+            min_reward_per_byte,
+            max_size,
+            token_allowlist,
+            token_denylist,
+            da_layer_preference,
+            bid_without_cost_estimate,
+            prices,
             otel_exporter_otlp_endpoint,
             host,
             port,
@@ -240,23 +608,94 @@ async fn main() -> eyre::Result<()> {
                 }
             };
             let eip4844_signer = kuda_operator::kms::get_signer(eip4844_kms).await?;
-            let eip4844_client = Arc::new(Eip4844Client::new(
+            let eip4844_client = Eip4844Client::new(
                 eip4844_signer,
                 eip4844_to_address,
                 eip4844_rpc_url,
                 eip4844_beacon_url,
-            )?);
-            let celestia_client = Arc::new(
-                CelestiaClient::new(&celestia_rpc_url, celestia_auth_token.as_deref()).await?,
-            );
+                ReplacementConfig {
+                    inclusion_timeout: Duration::from_secs(eip4844_inclusion_timeout),
+                    max_replacements: eip4844_max_replacements,
+                },
+                eip4844_multi_blob_payloads,
+            )?;
+            let celestia_client = CelestiaClient::new(
+                &celestia_rpc_url,
+                celestia_auth_token.as_deref(),
+                celestia_gas_price,
+            )
+            .await?;
+            let mut da_registry = DaRegistry::default();
+            da_registry
+                .register(celestia_client)
+                .register(eip4844_client);
+            let mut max_concurrent = HashMap::from([
+                (DaLayer::Celestia, celestia_max_concurrent_tasks),
+                (DaLayer::Eip4844, eip4844_max_concurrent_tasks),
+            ]);
+            if let (true, Some(avail_rpc_url), Some(avail_seed)) =
+                (experimental_avail, avail_rpc_url, avail_seed)
+            {
+                da_registry.register(AvailClient::new(
+                    avail_rpc_url,
+                    avail_seed,
+                    avail_app_id,
+                    Duration::from_secs(avail_inclusion_timeout),
+                )?);
+                max_concurrent.insert(DaLayer::Avail, avail_max_concurrent_tasks);
+            }
+            if let (true, Some(eigenda_disperser_url)) =
+                (experimental_eigenda, eigenda_disperser_url)
+            {
+                da_registry.register(EigenDaClient::new(
+                    &eigenda_disperser_url,
+                    Duration::from_secs(eigenda_poll_interval),
+                    Duration::from_secs(eigenda_confirmation_timeout),
+                    eigenda_price_per_symbol,
+                )?);
+                max_concurrent.insert(DaLayer::EigenDa, eigenda_max_concurrent_tasks);
+            }
+
+            let bidding_policy = Arc::new(DefaultBiddingPolicy::new(BiddingConfig {
+                min_reward_per_byte,
+                max_size,
+                token_allowlist,
+                token_denylist,
+                da_layer_preference,
+                bid_without_cost_estimate,
+            }));
 
             let config = RunConfig {
-                aggregator_url,
+                aggregator_urls: aggregator_url,
+                aggregator_mode,
+                aggregator_signer: aggregator_address
+                    .map_or(AggregatorSigner::OnChain, AggregatorSigner::Address),
                 operator_signer,
                 kuda_instance,
                 operator,
-                celestia_client,
-                eip4844_client,
+                da_registry: Arc::new(da_registry),
+                challenge_poll_interval: Duration::from_secs(challenge_poll_interval),
+                operator_monitor_interval: Duration::from_secs(operator_monitor_interval),
+                bond_top_up: bond_top_up_threshold.map(|threshold| BondTopUp { threshold }),
+                watchtower_poll_interval: watchtower
+                    .then(|| Duration::from_secs(watchtower_poll_interval)),
+                slashing_poll_interval: Duration::from_secs(slashing_poll_interval),
+                slashing_log_path,
+                slashing_alert_webhook,
+                journal_path,
+                bidding_policy,
+                price_source: Arc::new(prices.price_source(&kuda_rpc_url)),
+                retry_config: RetryConfig {
+                    initial_backoff: Duration::from_secs(retry_initial_backoff),
+                    max_backoff: Duration::from_secs(retry_max_backoff),
+                    deadline: Duration::from_secs(retry_deadline),
+                },
+                verify_posts,
+                executor_config: ExecutorConfig {
+                    max_concurrent,
+                    max_queued: max_queued_tasks,
+                    queue_full_policy,
+                },
                 otel_exporter_otlp_endpoint,
                 host,
                 port,
@@ -273,6 +712,49 @@ async fn main() -> eyre::Result<()> {
 
             register(config).await?;
         }
+        KudaOperatorCommand::Status { format, prices } => {
+            let price_source = prices.price_source(&kuda_rpc_url);
+            lifecycle::status(&operator, &price_source, format).await?;
+        }
+        KudaOperatorCommand::Unregister => {
+            lifecycle::unregister(&operator).await?;
+        }
+        KudaOperatorCommand::Bond { amount } => {
+            lifecycle::submit_bond(&operator, amount).await?;
+        }
+        KudaOperatorCommand::Vault {
+            stake_updates_path,
+            command,
+        } => match command {
+            VaultCommand::Create {
+                asset,
+                name,
+                symbol,
+                vault_impl,
+            } => {
+                staking::create_vault(&operator, asset, name, symbol, vault_impl).await?;
+            }
+            VaultCommand::Deposit { vault, amount } => {
+                staking::deposit(&operator, vault, &amount).await?;
+            }
+            VaultCommand::Stake { vault } => {
+                staking::request_stake_update(&operator, &stake_updates_path, vault, true).await?;
+            }
+            VaultCommand::Unstake { vault } => {
+                staking::request_stake_update(&operator, &stake_updates_path, vault, false).await?;
+            }
+            VaultCommand::Finalize { vault } => {
+                staking::finalize_stake_updates(&operator, &stake_updates_path, vault).await?;
+            }
+        },
+        KudaOperatorCommand::Withdraw { command } => match command {
+            WithdrawCommand::Start { token } => {
+                lifecycle::start_withdraw(&operator, token).await?;
+            }
+            WithdrawCommand::Finish { withdraw_hash } => {
+                lifecycle::finish_withdraw(&operator, withdraw_hash).await?;
+            }
+        },
     }
 
     Ok(())
@@ -0,0 +1,232 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{utils::format_units, Address, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use metrics::{counter, gauge};
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    operator::{serialize_u256, Operator, Vault},
+    price::PriceSource,
+    valuation::{value_stake, StakeValuation},
+};
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OperatorStatus {
+    pub jailed: bool,
+    #[serde(serialize_with = "serialize_u256")]
+    pub bond: U256,
+    #[serde(serialize_with = "serialize_u256")]
+    pub min_bond: U256,
+    pub stake: HashMap<Address, Vault>,
+    pub valuation: StakeValuation,
+}
+
+impl OperatorStatus {
+    pub async fn fetch<T: Transport + Clone, P: Provider<T> + Clone>(
+        operator: &Operator<T, P>,
+        price_source: &PriceSource,
+    ) -> eyre::Result<Self> {
+        let stake = operator.stake().await?;
+        let valuation = value_stake(&stake, price_source).await;
+        Ok(OperatorStatus {
+            jailed: operator.is_jailed().await?,
+            bond: operator.bond().await?,
+            min_bond: operator.min_bond().await?,
+            stake,
+            valuation,
+        })
+    }
+
+    /// What is wrong with the operator, worst first
+    fn problems(&self) -> eyre::Result<Vec<String>> {
+        let mut problems = Vec::new();
+        if self.jailed {
+            problems.push("Operator is jailed".to_string());
+        }
+        if self.bond < self.min_bond {
+            problems.push(format!(
+                "Operator bond {} ETH is below the minimum of {} ETH",
+                format_units(self.bond, "ether")?,
+                format_units(self.min_bond, "ether")?
+            ));
+        }
+        if self.stake.values().all(|vault| vault.amount.is_zero()) {
+            problems.push("Operator has no stake".to_string());
+        }
+        Ok(problems)
+    }
+}
+
+/// The last status polled by the monitor
+#[derive(Debug, Clone, Default)]
+pub enum MonitoredStatus {
+    #[default]
+    Pending,
+    Ok(OperatorStatus),
+    Error(String),
+}
+
+pub type SharedStatus = Arc<RwLock<MonitoredStatus>>;
+
+/// Tops the operator bond back up to `threshold` whenever it drops below it
+#[derive(Debug, Clone, Copy)]
+pub struct BondTopUp {
+    pub threshold: U256,
+}
+
+impl BondTopUp {
+    /// The bond to submit to get from `bond` back to the threshold, if it is below
+    fn amount(&self, bond: U256) -> Option<U256> {
+        (bond < self.threshold).then(|| self.threshold - bond)
+    }
+}
+
+pub struct OperatorMonitor<T: Transport + Clone, P: Provider<T>> {
+    pub operator: Arc<Operator<T, P>>,
+    pub poll_interval: Duration,
+    pub bond_top_up: Option<BondTopUp>,
+    pub price_source: Arc<PriceSource>,
+    pub status: SharedStatus,
+}
+
+impl<T: Transport + Clone, P: Provider<T> + Clone> OperatorMonitor<T, P> {
+    /// Periodically polls the jail state, bond and stake of the operator, valuing the stake in USD and ETH
+    pub async fn run(&self, cancellation_token: CancellationToken) {
+        loop {
+            let status = match self.poll().await {
+                Ok(status) => MonitoredStatus::Ok(status),
+                Err(e) => {
+                    tracing::error!("Operator monitor error: {e:?}");
+                    MonitoredStatus::Error(e.to_string())
+                }
+            };
+            *self.status.write().await = status;
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    tracing::info!("Operator monitor cancelled");
+                    return;
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    async fn poll(&self) -> eyre::Result<OperatorStatus> {
+        let mut status = OperatorStatus::fetch(&self.operator, &self.price_source).await?;
+        let OperatorStatus {
+            jailed,
+            bond,
+            min_bond,
+            ..
+        } = status;
+
+        gauge!("operator_jailed").set(if jailed { 1 } else { 0 });
+        gauge!("operator_bond").set(f64::from(bond));
+        gauge!("operator_min_bond").set(f64::from(min_bond));
+        for (vault_address, vault) in &status.stake {
+            gauge!(
+                "operator_vault_stake",
+                "vault" => vault_address.to_string(),
+                "symbol" => vault.symbol.clone()
+            )
+            .set(f64::from(vault.amount));
+        }
+        gauge!("operator_stake_usd").set(status.valuation.total_usd);
+        if let Some(total_eth) = status.valuation.total_eth {
+            gauge!("operator_stake_eth").set(total_eth);
+        }
+
+        for problem in status.problems()? {
+            tracing::error!("{problem} ({})", self.operator.operator_address);
+        }
+
+        if let Some(top_up) = self.bond_top_up {
+            if let Some(amount) = top_up.amount(bond) {
+                tracing::warn!(
+                    "Topping up operator bond with {} ETH",
+                    format_units(amount, "ether")?
+                );
+                match self.operator.submit_operator_bond(amount).await {
+                    Ok(_) => {
+                        counter!("operator_bond_top_up").increment(1);
+                        status.bond = self.operator.bond().await?;
+                        gauge!("operator_bond").set(f64::from(status.bond));
+                    }
+                    Err(e) => tracing::error!("Failed to top up operator bond: {e:?}"),
+                }
+            }
+        }
+
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status() -> OperatorStatus {
+        OperatorStatus {
+            jailed: false,
+            bond: U256::from(2),
+            min_bond: U256::from(1),
+            stake: HashMap::from([(
+                Address::ZERO,
+                Vault {
+                    symbol: "kETH".to_string(),
+                    name: "Vault".to_string(),
+                    asset: Address::ZERO,
+                    decimals: 18,
+                    amount: U256::from(1),
+                },
+            )]),
+            valuation: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_problems() {
+        let mut status = status();
+        assert!(status.problems().unwrap().is_empty());
+
+        status.bond = U256::from(1);
+        assert!(status.problems().unwrap().is_empty());
+        status.bond = U256::ZERO;
+        assert_eq!(
+            status.problems().unwrap(),
+            vec!["Operator bond 0.000000000000000000 ETH is below the minimum of 0.000000000000000001 ETH"]
+        );
+
+        status.jailed = true;
+        status
+            .stake
+            .values_mut()
+            .for_each(|vault| vault.amount = U256::ZERO);
+        let problems = status.problems().unwrap();
+        assert_eq!(problems.len(), 3);
+        assert_eq!(problems[0], "Operator is jailed");
+        assert_eq!(problems[2], "Operator has no stake");
+
+        status.stake.clear();
+        assert_eq!(status.problems().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_bond_top_up_amount() {
+        let top_up = BondTopUp {
+            threshold: U256::from(10),
+        };
+        assert_eq!(top_up.amount(U256::ZERO), Some(U256::from(10)));
+        assert_eq!(top_up.amount(U256::from(9)), Some(U256::from(1)));
+        assert_eq!(top_up.amount(U256::from(10)), None);
+        assert_eq!(top_up.amount(U256::from(11)), None);
+    }
+}
@@ -1,20 +1,32 @@
 use std::{collections::HashMap, sync::Arc};
 
 use alloy::{
-    primitives::{utils::format_units, Address, Bytes, TxHash, U256},
+    eips::BlockId,
+    primitives::{utils::format_units, Address, Bytes, TxHash, B256, U256},
     providers::Provider,
+    sol_types::SolCall,
     transports::Transport,
 };
-use serde::Serialize;
+use eyre::{OptionExt, WrapErr};
+use serde::{Deserialize, Serialize};
 
 use crate::contracts::{
-    core::Core::CoreInstance, kuda::Kuda::KudaInstance, vault::Vault::VaultInstance,
+    core::{
+        Core::{self, CoreInstance},
+        Operator::{QueuedStakeUpdate, StakeUpdateRequest},
+        VaultLib,
+    },
+    erc20_mintable::ERC20Mintable::ERC20MintableInstance,
+    kuda::Kuda::{startFullWithdrawCall, KudaInstance},
+    vault::Vault::VaultInstance,
 };
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Vault {
     pub symbol: String,
     pub name: String,
+    pub asset: Address,
+    pub decimals: u8,
     #[serde(serialize_with = "serialize_u256")]
     pub amount: U256,
 }
@@ -26,6 +38,12 @@ where
     serializer.serialize_str(&value.to_string())
 }
 
+/// The part of a `callTracer` trace of `debug_traceTransaction` we read: the return data
+#[derive(Debug, Deserialize)]
+struct CallTrace {
+    output: Option<Bytes>,
+}
+
 pub struct Operator<T: Transport + Clone, P: Provider<T>> {
     pub operator_address: Address,
     pub kuda_address: Address,
@@ -92,6 +110,243 @@ impl<T: Transport + Clone, P: Provider<T> + Clone> Operator<T, P> {
         Ok(receipt.transaction_hash)
     }
 
+    #[tracing::instrument(skip(self))]
+    pub async fn unregister(&self) -> eyre::Result<TxHash> {
+        let receipt = self
+            .core_instance
+            .unregisterOperatorFromDSS(self.kuda_address)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        tracing::info!(
+            "Operator unregistered with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok(receipt.transaction_hash)
+    }
+
+    /// Starts withdrawing our full balance of `token` from KUDA, returning the withdraw hash
+    /// needed to finish it after `FULL_WITHDRAW_DELAY`, the transaction hash and its block
+    #[tracing::instrument(skip(self))]
+    pub async fn start_full_withdraw(&self, token: Address) -> eyre::Result<(B256, TxHash, u64)> {
+        let receipt = self
+            .kuda_instance
+            .startFullWithdraw(token)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        let tx_hash = receipt.transaction_hash;
+        if !receipt.status() {
+            return Err(eyre::eyre!("Full withdraw transaction {tx_hash} reverted"));
+        }
+        tracing::info!("Full withdraw started with tx hash: {tx_hash}");
+        let block_number = receipt
+            .block_number
+            .ok_or_eyre("No block number in receipt")?;
+        let withdraw_hash = self
+            .mined_withdraw_hash(token, receipt.from, tx_hash, block_number)
+            .await
+            .wrap_err_with(|| {
+                format!(
+                    "Full withdraw started with tx hash {tx_hash}, but its withdraw hash could \
+                     not be read back. Trace the transaction on a node supporting \
+                     debug_traceTransaction"
+                )
+            })?;
+        Ok((withdraw_hash, tx_hash, block_number))
+    }
+
+    /// The withdraw hash returned by the `startFullWithdraw` transaction `tx_hash`, mined in
+    /// `block_number`. A simulation before sending could return another one if the contract state
+    /// changes, so the call is replayed from `from` on the state the transaction ran against
+    async fn mined_withdraw_hash(
+        &self,
+        token: Address,
+        from: Address,
+        tx_hash: TxHash,
+        block_number: u64,
+    ) -> eyre::Result<B256> {
+        let replayed = self
+            .kuda_instance
+            .startFullWithdraw(token)
+            .from(from)
+            .block(BlockId::number(block_number.saturating_sub(1)))
+            .call()
+            .await;
+        match replayed {
+            Ok(returns) => Ok(returns.withdrawHash),
+            Err(e) => {
+                tracing::warn!("Could not replay full withdraw {tx_hash}, tracing it: {e:?}");
+                self.traced_withdraw_hash(tx_hash).await
+            }
+        }
+    }
+
+    /// The withdraw hash returned by the `startFullWithdraw` transaction `tx_hash`, read from its
+    /// trace
+    async fn traced_withdraw_hash(&self, tx_hash: TxHash) -> eyre::Result<B256> {
+        let trace: CallTrace = self
+            .provider
+            .raw_request(
+                "debug_traceTransaction".into(),
+                (tx_hash, serde_json::json!({ "tracer": "callTracer" })),
+            )
+            .await?;
+        let output = trace.output.ok_or_eyre("No output in transaction trace")?;
+        Ok(startFullWithdrawCall::abi_decode_returns(&output, true)?.withdrawHash)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn finish_full_withdraw(&self, withdraw_hash: B256) -> eyre::Result<TxHash> {
+        let receipt = self
+            .kuda_instance
+            .finishFullWithdraw(withdraw_hash)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        tracing::info!(
+            "Full withdraw finished with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok(receipt.transaction_hash)
+    }
+
+    /// Deploys a vault for `asset` with the given implementation, or the standard one if zero
+    #[tracing::instrument(skip(self))]
+    pub async fn deploy_vault(
+        &self,
+        asset: Address,
+        name: String,
+        symbol: String,
+        vault_impl: Address,
+    ) -> eyre::Result<(Address, TxHash)> {
+        let decimals = ERC20MintableInstance::new(asset, self.provider.clone())
+            .decimals()
+            .call()
+            .await?
+            ._0;
+        let config = VaultLib::Config {
+            asset,
+            decimals,
+            operator: self.operator_address,
+            name,
+            symbol,
+            extraData: Bytes::default(),
+        };
+        let receipt = self
+            .core_instance
+            .deployVaults(vec![config], vault_impl)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        let vault = receipt
+            .inner
+            .logs()
+            .iter()
+            .find_map(|log| log.log_decode::<Core::DeployedVault>().ok())
+            .ok_or_else(|| eyre::eyre!("No DeployedVault event in vault deployment"))?
+            .inner
+            .data
+            .vault;
+        tracing::info!(
+            "Vault {vault} deployed with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok((vault, receipt.transaction_hash))
+    }
+
+    /// Deposits `amount` of the vault's asset, approving the vault to spend it first
+    #[tracing::instrument(skip(self))]
+    pub async fn deposit(&self, vault: Address, amount: U256) -> eyre::Result<TxHash> {
+        let vault_instance = VaultInstance::new(vault, self.provider.clone());
+        let asset = vault_instance.asset().call().await?._0;
+        ERC20MintableInstance::new(asset, self.provider.clone())
+            .approve(vault, amount)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        let receipt = vault_instance
+            .deposit_0(amount, self.operator_address)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        tracing::info!(
+            "Deposited to vault {vault} with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok(receipt.transaction_hash)
+    }
+
+    /// Requests staking or unstaking `vault` to KUDA, returning the queued update to finalize
+    #[tracing::instrument(skip(self))]
+    pub async fn request_stake_update(
+        &self,
+        vault: Address,
+        to_stake: bool,
+    ) -> eyre::Result<(QueuedStakeUpdate, TxHash)> {
+        let request = StakeUpdateRequest {
+            vault,
+            dss: self.kuda_address,
+            toStake: to_stake,
+        };
+        let receipt = self
+            .core_instance
+            .requestUpdateVaultStakeInDSS(request)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        let queued_stake_update = receipt
+            .inner
+            .logs()
+            .iter()
+            .find_map(|log| log.log_decode::<Core::RequestedStakeUpdate>().ok())
+            .ok_or_else(|| eyre::eyre!("No RequestedStakeUpdate event in stake update"))?
+            .inner
+            .data
+            .updateRequest;
+        tracing::info!(
+            "Stake update requested with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok((queued_stake_update, receipt.transaction_hash))
+    }
+
+    #[tracing::instrument(skip(self, queued_stake_update))]
+    pub async fn finalize_stake_update(
+        &self,
+        queued_stake_update: QueuedStakeUpdate,
+    ) -> eyre::Result<TxHash> {
+        let receipt = self
+            .core_instance
+            .finalizeUpdateVaultStakeInDSS(queued_stake_update)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        tracing::info!(
+            "Stake update finalized with tx hash: {}",
+            receipt.transaction_hash
+        );
+        Ok(receipt.transaction_hash)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn active_vaults(&self) -> eyre::Result<Vec<Address>> {
+        Ok(self
+            .kuda_instance
+            .getActiveVaults(self.operator_address)
+            .call()
+            .await?
+            ._0)
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn is_registered(&self) -> eyre::Result<bool> {
         let is_registered = self
@@ -103,7 +358,31 @@ impl<T: Transport + Clone, P: Provider<T> + Clone> Operator<T, P> {
         Ok(is_registered)
     }
 
-    // TODO: Normalize to ETH
+    #[tracing::instrument(skip(self))]
+    pub async fn is_jailed(&self) -> eyre::Result<bool> {
+        Ok(self
+            .kuda_instance
+            .isOperatorJailed(self.operator_address)
+            .call()
+            .await?
+            ._0)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn bond(&self) -> eyre::Result<U256> {
+        Ok(self
+            .kuda_instance
+            .operatorBond(self.operator_address)
+            .call()
+            .await?
+            .bond)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn min_bond(&self) -> eyre::Result<U256> {
+        Ok(self.kuda_instance.MIN_OPERATOR_BOND().call().await?._0)
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn stake(&self) -> eyre::Result<HashMap<Address, Vault>> {
         let operator_vaults = self
@@ -117,10 +396,14 @@ impl<T: Transport + Clone, P: Provider<T> + Clone> Operator<T, P> {
             let vault_instance = VaultInstance::new(vault_address, self.provider.clone());
             let symbol = vault_instance.symbol().call().await?._0;
             let name = vault_instance.name().call().await?._0;
+            let asset = vault_instance.asset().call().await?._0;
+            let decimals = vault_instance.decimals().call().await?._0;
             let amount = vault_instance.totalAssets().call().await?._0;
             let vault = Vault {
                 symbol,
                 name,
+                asset,
+                decimals,
                 amount,
             };
             stake.insert(vault_address, vault);
@@ -0,0 +1,329 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, I256, U256},
+    providers::RootProvider,
+    transports::http::{Client, Http},
+};
+use serde::Deserialize;
+use url::Url;
+
+use crate::contracts::price_feed::PriceFeed::PriceFeedInstance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Asset {
+    Eth,
+    Tia,
+    Avail,
+    Token(Address),
+}
+
+impl Asset {
+    /// Decimals of the native assets; tokens have to be looked up on-chain
+    pub fn native_decimals(&self) -> Option<u8> {
+        match self {
+            Asset::Eth => Some(18),
+            Asset::Tia => Some(6),
+            Asset::Avail => Some(18),
+            Asset::Token(_) => None,
+        }
+    }
+}
+
+impl FromStr for Asset {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "eth" => Ok(Asset::Eth),
+            "tia" => Ok(Asset::Tia),
+            "avail" => Ok(Asset::Avail),
+            _ => Ok(Asset::Token(Address::from_str(s)?)),
+        }
+    }
+}
+
+impl Display for Asset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Asset::Eth => write!(f, "ETH"),
+            Asset::Tia => write!(f, "TIA"),
+            Asset::Avail => write!(f, "AVAIL"),
+            Asset::Token(address) => write!(f, "{address}"),
+        }
+    }
+}
+
+/// USD price of one whole unit of an asset, parsed from `<ASSET>=<PRICE>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssetPrice {
+    pub asset: Asset,
+    pub usd: f64,
+}
+
+impl FromStr for AssetPrice {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (asset, usd) = s
+            .split_once('=')
+            .ok_or_else(|| eyre::eyre!("Expected <ASSET>=<PRICE>, got {s}"))?;
+        Ok(AssetPrice {
+            asset: Asset::from_str(asset.trim())?,
+            usd: f64::from_str(usd.trim())?,
+        })
+    }
+}
+
+/// Address of a Chainlink-style USD price feed for an asset, parsed from `<ASSET>=<FEED>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AssetFeed {
+    pub asset: Asset,
+    pub feed: Address,
+}
+
+impl FromStr for AssetFeed {
+    type Err = eyre::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (asset, feed) = s
+            .split_once('=')
+            .ok_or_else(|| eyre::eyre!("Expected <ASSET>=<FEED>, got {s}"))?;
+        Ok(AssetFeed {
+            asset: Asset::from_str(asset.trim())?,
+            feed: Address::from_str(feed.trim())?,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct HttpPrice {
+    usd: f64,
+}
+
+pub enum PriceSource {
+    /// Fixed USD prices from the operator's configuration
+    Static(HashMap<Asset, f64>),
+    /// Chainlink-style USD price feeds, read through `latestRoundData`. Answers older than
+    /// `max_age` are rejected
+    Feeds {
+        provider: RootProvider<Http<Client>>,
+        feeds: HashMap<Asset, Address>,
+        max_age: Duration,
+    },
+    /// An HTTP endpoint answering `GET <url>/<ASSET>` with `{"usd": <price>}`
+    Http { client: reqwest::Client, url: Url },
+    /// Sources tried in order until one has a price
+    Fallback(Vec<PriceSource>),
+}
+
+impl PriceSource {
+    pub fn from_prices(prices: &[AssetPrice]) -> Self {
+        PriceSource::Static(
+            prices
+                .iter()
+                .map(|price| (price.asset, price.usd))
+                .collect(),
+        )
+    }
+
+    pub fn from_feeds(rpc_url: Url, feeds: &[AssetFeed], max_age: Duration) -> Self {
+        PriceSource::Feeds {
+            provider: RootProvider::new_http(rpc_url),
+            feeds: feeds.iter().map(|feed| (feed.asset, feed.feed)).collect(),
+            max_age,
+        }
+    }
+
+    pub fn from_url(url: Url) -> Self {
+        PriceSource::Http {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    /// USD price of one whole unit of `asset`
+    pub async fn usd_price(&self, asset: &Asset) -> eyre::Result<f64> {
+        match self {
+            PriceSource::Static(prices) => prices
+                .get(asset)
+                .copied()
+                .ok_or_else(|| eyre::eyre!("No price configured for {asset}")),
+            PriceSource::Feeds {
+                provider,
+                feeds,
+                max_age,
+            } => {
+                let feed = feeds
+                    .get(asset)
+                    .ok_or_else(|| eyre::eyre!("No price feed configured for {asset}"))?;
+                let feed = PriceFeedInstance::new(*feed, provider);
+                let decimals = feed.decimals().call().await?._0;
+                let round = feed.latestRoundData().call().await?;
+                let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+                feed_price(round.answer, decimals, round.updatedAt, *max_age, now)
+                    .map_err(|e| eyre::eyre!("Price feed of {asset}: {e}"))
+            }
+            PriceSource::Http { client, url } => {
+                let url = format!("{}/{asset}", url.as_str().trim_end_matches('/'));
+                let price = client
+                    .get(url)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<HttpPrice>()
+                    .await?;
+                Ok(price.usd)
+            }
+            PriceSource::Fallback(sources) => {
+                let mut errors = Vec::new();
+                for source in sources {
+                    match Box::pin(source.usd_price(asset)).await {
+                        Ok(price) => return Ok(price),
+                        Err(e) => errors.push(e.to_string()),
+                    }
+                }
+                Err(eyre::eyre!("No price for {asset}: {}", errors.join("; ")))
+            }
+        }
+    }
+}
+
+/// Price from a feed `answer` with `decimals` decimals, failing if it is not positive or was
+/// updated more than `max_age` before `now`
+fn feed_price(
+    answer: I256,
+    decimals: u8,
+    updated_at: U256,
+    max_age: Duration,
+    now: u64,
+) -> eyre::Result<f64> {
+    if !answer.is_positive() {
+        return Err(eyre::eyre!("Answered {answer}"));
+    }
+    let age = U256::from(now).saturating_sub(updated_at);
+    if age > U256::from(max_age.as_secs()) {
+        return Err(eyre::eyre!(
+            "Answer is {age} seconds old, more than {} seconds",
+            max_age.as_secs()
+        ));
+    }
+    Ok(f64::from(answer.into_raw()) / 10f64.powi(decimals.into()))
+}
+
+/// `amount` base units of an asset with `decimals` decimals, in whole units
+pub fn whole_units(amount: U256, decimals: u8) -> f64 {
+    f64::from(amount) / 10f64.powi(decimals.into())
+}
+
+/// Converts `amount` base units of an asset into base units of another, given their USD prices.
+/// Rounds up, so that a cost is never underestimated
+pub fn convert(
+    amount: U256,
+    from_decimals: u8,
+    from_usd: f64,
+    to_decimals: u8,
+    to_usd: f64,
+) -> eyre::Result<U256> {
+    if from_usd <= 0.0 || to_usd <= 0.0 {
+        return Err(eyre::eyre!("Price must be positive"));
+    }
+    let converted =
+        whole_units(amount, from_decimals) * from_usd / to_usd * 10f64.powi(to_decimals.into());
+    Ok(U256::try_from(converted.ceil())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_asset_price_from_str() {
+        let price = AssetPrice::from_str("eth=2500.5").unwrap();
+        assert_eq!(price.asset, Asset::Eth);
+        assert_eq!(price.usd, 2500.5);
+
+        let price = AssetPrice::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48=1").unwrap();
+        assert_eq!(
+            price.asset,
+            Asset::Token(Address::from_str("0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48").unwrap())
+        );
+
+        assert!(AssetPrice::from_str("eth").is_err());
+    }
+
+    #[test]
+    fn test_convert() {
+        // 0.5 ETH at $2000 is 1000 USDC
+        let amount = U256::from(500_000_000_000_000_000u64);
+        let converted = convert(amount, 18, 2000.0, 6, 1.0).unwrap();
+        assert_eq!(converted, U256::from(1_000_000_000));
+
+        // 2 TIA at $4 is 0.00390625 ETH at $2048
+        let converted = convert(U256::from(2_000_000), 6, 4.0, 18, 2048.0).unwrap();
+        assert_eq!(converted, U256::from(3_906_250_000_000_000u64));
+
+        // 1 utia at $4 is 1.33 base units of a 6 decimals token at $3
+        let converted = convert(U256::from(1), 6, 4.0, 6, 3.0).unwrap();
+        assert_eq!(converted, U256::from(2));
+
+        assert!(convert(amount, 18, 2000.0, 6, 0.0).is_err());
+        assert!(convert(amount, 18, 0.0, 6, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_feed_price() {
+        let max_age = Duration::from_secs(3600);
+        let answer = I256::try_from(250_000_000_000i64).unwrap();
+        let updated_at = U256::from(1_700_000_000);
+
+        assert_eq!(
+            feed_price(answer, 8, updated_at, max_age, 1_700_003_600).unwrap(),
+            2500.0
+        );
+        assert!(feed_price(answer, 8, updated_at, max_age, 1_700_003_601).is_err());
+        assert!(feed_price(I256::ZERO, 8, updated_at, max_age, 1_700_000_000).is_err());
+        assert!(feed_price(-answer, 8, updated_at, max_age, 1_700_000_000).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_price_source_with_fallback() {
+        use axum::{extract::Path, routing::get, Json, Router};
+
+        let app = Router::new().route(
+            "/:asset",
+            get(|Path(asset): Path<String>| async move {
+                match asset.as_str() {
+                    "ETH" => Ok(Json(serde_json::json!({ "usd": 2500.0 }))),
+                    _ => Err(axum::http::StatusCode::NOT_FOUND),
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let http = PriceSource::from_url(url);
+        assert_eq!(http.usd_price(&Asset::Eth).await.unwrap(), 2500.0);
+        assert!(http.usd_price(&Asset::Tia).await.is_err());
+
+        let source = PriceSource::Fallback(vec![
+            http,
+            PriceSource::from_prices(&[AssetPrice {
+                asset: Asset::Tia,
+                usd: 5.0,
+            }]),
+        ]);
+        assert_eq!(source.usd_price(&Asset::Eth).await.unwrap(), 2500.0);
+        assert_eq!(source.usd_price(&Asset::Tia).await.unwrap(), 5.0);
+        assert!(source
+            .usd_price(&Asset::Token(Address::ZERO))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    time::{Duration, SystemTime},
+};
+
+use metrics::counter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// Timeouts and connectivity problems, worth retrying as is
+    Transient,
+    /// The transaction was priced too low, worth retrying with fresh fees
+    Underpriced,
+    /// The nonce we used is out of sync with the chain
+    NonceConflict,
+    /// Retrying would fail the same way
+    Fatal,
+}
+
+impl Display for ErrorClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorClass::Transient => write!(f, "transient"),
+            ErrorClass::Underpriced => write!(f, "underpriced"),
+            ErrorClass::NonceConflict => write!(f, "nonce_conflict"),
+            ErrorClass::Fatal => write!(f, "fatal"),
+        }
+    }
+}
+
+const NONCE_CONFLICT_PATTERNS: &[&str] = &["nonce too low", "nonce too high", "invalid nonce"];
+const UNDERPRICED_PATTERNS: &[&str] = &[
+    "underpriced",
+    "fee too low",
+    "max fee per gas less than block base fee",
+    "max fee per blob gas less than block blob gas fee",
+];
+const FATAL_PATTERNS: &[&str] = &[
+    "commitment does not match",
+    "namespace is required",
+    "insufficient funds",
+    "blob too large",
+    "exceeds the maximum",
+];
+
+/// Classifies an error from its message chain
+pub fn classify(error: &eyre::Report) -> ErrorClass {
+    let message = error
+        .chain()
+        .map(|cause| cause.to_string().to_lowercase())
+        .collect::<Vec<_>>()
+        .join(": ");
+    classify_message(&message)
+}
+
+pub fn classify_message(message: &str) -> ErrorClass {
+    let message = message.to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|pattern| message.contains(pattern));
+    if matches(NONCE_CONFLICT_PATTERNS) {
+        ErrorClass::NonceConflict
+    } else if matches(UNDERPRICED_PATTERNS) {
+        ErrorClass::Underpriced
+    } else if matches(FATAL_PATTERNS) {
+        ErrorClass::Fatal
+    } else {
+        ErrorClass::Transient
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// How long after a task's submission time we keep retrying
+    pub deadline: Duration,
+}
+
+impl RetryConfig {
+    /// Exponential backoff before retry number `attempt`, starting at 0
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+
+    /// The time after which a task submitted at `submission_time` (in seconds) is given up on
+    pub fn deadline_for(&self, submission_time: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(submission_time) + self.deadline
+    }
+}
+
+/// Runs `operation` until it succeeds, fails fatally, or the next retry would pass `deadline`
+pub async fn retry<R, F, Fut>(
+    config: &RetryConfig,
+    deadline: SystemTime,
+    label: &str,
+    mut operation: F,
+) -> eyre::Result<R>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = eyre::Result<R>>,
+{
+    let mut attempt = 0;
+    loop {
+        let error = match operation().await {
+            Ok(result) => return Ok(result),
+            Err(error) => error,
+        };
+
+        let class = classify(&error);
+        if class == ErrorClass::Fatal {
+            return Err(error);
+        }
+
+        let backoff = config.backoff(attempt);
+        if SystemTime::now() + backoff > deadline {
+            return Err(error.wrap_err(format!("{label}: retry deadline reached")));
+        }
+
+        counter!("retry", "operation" => label.to_string(), "class" => class.to_string())
+            .increment(1);
+        tracing::warn!("{label} failed ({class}), retrying in {backoff:?}: {error:?}");
+        tokio::time::sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    fn config() -> RetryConfig {
+        RetryConfig {
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(4),
+            deadline: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn test_classify_message() {
+        assert_eq!(
+            classify_message("server returned an error response: nonce too low"),
+            ErrorClass::NonceConflict
+        );
+        assert_eq!(
+            classify_message("replacement transaction underpriced"),
+            ErrorClass::Underpriced
+        );
+        assert_eq!(
+            classify_message("Provided commitment does not match computed commitment"),
+            ErrorClass::Fatal
+        );
+        assert_eq!(
+            classify_message("transaction was not confirmed within the timeout"),
+            ErrorClass::Transient
+        );
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = config();
+        assert_eq!(config.backoff(0), Duration::from_millis(1));
+        assert_eq!(config.backoff(1), Duration::from_millis(2));
+        assert_eq!(config.backoff(2), Duration::from_millis(4));
+        assert_eq!(config.backoff(10), Duration::from_millis(4));
+        assert_eq!(config.backoff(u32::MAX), Duration::from_millis(4));
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let config = config();
+        let deadline = SystemTime::now() + config.deadline;
+
+        let attempts = AtomicU32::new(0);
+        let result = retry(&config, deadline, "test", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(eyre::eyre!("connection reset"))
+            } else {
+                Ok(42)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+
+        let attempts = AtomicU32::new(0);
+        let result: eyre::Result<()> = retry(&config, deadline, "test", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(eyre::eyre!("insufficient funds"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        let result: eyre::Result<()> = retry(&config, SystemTime::now(), "test", || async {
+            Err(eyre::eyre!("connection reset"))
+        })
+        .await;
+        assert!(result.is_err());
+    }
+}
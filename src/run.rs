@@ -1,6 +1,8 @@
 use std::{
     net::{IpAddr, SocketAddr},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use alloy::{providers::Provider, transports::Transport};
@@ -20,20 +22,52 @@ use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Env
 use url::Url;
 
 use crate::{
+    bidding::BiddingPolicy,
+    challenge::ChallengeResponder,
     contracts::kuda::Kuda::KudaInstance,
-    da::{celestia::CelestiaClient, eip4844::Eip4844Client},
+    cost::CostEstimator,
+    da::registry::DaRegistry,
+    executor::{ExecutorConfig, TaskExecutor},
+    health::HealthState,
+    journal::{BlockCursor, Journal},
     kms::KmsSigner,
+    monitor::{BondTopUp, OperatorMonitor},
     operator::Operator,
-    socketio::socket_io,
+    price::PriceSource,
+    retry::RetryConfig,
+    settlement::SettlementKeeper,
+    slashing::{SlashingLog, SlashingWatcher},
+    socketio::{
+        aggregator::{AggregatorMode, Aggregators, TaskDeduplicator},
+        resubmit_pending_receipts,
+        signature::AggregatorSigner,
+        socket_io, TaskContext,
+    },
+    watchtower::Watchtower,
 };
 
 pub struct RunConfig<T: Transport + Clone, P: Provider<T>> {
-    pub aggregator_url: Url,
+    pub aggregator_urls: Vec<Url>,
+    pub aggregator_mode: AggregatorMode,
+    pub aggregator_signer: AggregatorSigner,
     pub operator_signer: Arc<dyn KmsSigner + Send + Sync + 'static>,
     pub kuda_instance: Arc<KudaInstance<T, P>>,
     pub operator: Arc<Operator<T, P>>,
-    pub celestia_client: Arc<CelestiaClient>,
-    pub eip4844_client: Arc<Eip4844Client>,
+    pub da_registry: Arc<DaRegistry>,
+    pub challenge_poll_interval: Duration,
+    pub operator_monitor_interval: Duration,
+    pub bond_top_up: Option<BondTopUp>,
+    /// Poll interval of the watchtower, which is disabled if not set
+    pub watchtower_poll_interval: Option<Duration>,
+    pub slashing_poll_interval: Duration,
+    pub slashing_log_path: PathBuf,
+    pub slashing_alert_webhook: Option<Url>,
+    pub journal_path: PathBuf,
+    pub bidding_policy: Arc<dyn BiddingPolicy>,
+    pub price_source: Arc<PriceSource>,
+    pub retry_config: RetryConfig,
+    pub verify_posts: bool,
+    pub executor_config: ExecutorConfig,
     pub otel_exporter_otlp_endpoint: Option<Url>,
     pub host: IpAddr,
     pub port: u16,
@@ -80,6 +114,14 @@ pub async fn run<T: Transport + Clone, P: Provider<T> + Clone + 'static>(
         "posting_intent",
         "Counts the number of posting intents received"
     );
+    describe_counter!(
+        "posting_intent_rejected",
+        "Counts the number of posting intents rejected by the bidding policy"
+    );
+    describe_counter!(
+        "da_cost_estimate_error",
+        "Counts the number of DA costs that could not be estimated for a posting intent, by DA layer"
+    );
     describe_counter!(
         "task_responsibility",
         "Counts the number of posting intents assigned"
@@ -92,9 +134,116 @@ pub async fn run<T: Transport + Clone, P: Provider<T> + Clone + 'static>(
         "task_responsibility_success",
         "Counts the number of assigned tasks that succeeded"
     );
+    describe_counter!(
+        "challenge_received",
+        "Counts the number of challenges raised against our tasks"
+    );
+    describe_counter!(
+        "counter_challenge_error",
+        "Counts the number of challenges we failed to counter"
+    );
+    describe_counter!(
+        "counter_challenge_missed",
+        "Counts the number of challenges we gave up on countering"
+    );
+    describe_counter!(
+        "counter_challenge_success",
+        "Counts the number of challenges we countered"
+    );
+    describe_counter!(
+        "retry",
+        "Counts the number of retried operations, by operation and error class"
+    );
+    describe_counter!(
+        "blob_verification_failed",
+        "Counts the number of posted blobs that could not be read back, by DA layer"
+    );
+    describe_counter!(
+        "eip4844_tx_replaced",
+        "Counts the number of blob transactions replaced with bumped fees"
+    );
+    describe_counter!(
+        "task_responsibility_duplicate",
+        "Counts the number of assigned tasks delivered again after being posted"
+    );
+    describe_counter!(
+        "task_receipt_sent",
+        "Counts the number of task receipts sent back to the aggregator"
+    );
+    describe_counter!(
+        "task_preflight_failed",
+        "Counts the number of assigned tasks not posted because a pre-flight check failed, by check"
+    );
+    describe_counter!(
+        "task_signature_invalid",
+        "Counts the number of assigned tasks rejected for an invalid aggregator signature"
+    );
+    describe_counter!(
+        "task_queue_rejected",
+        "Counts the number of assigned tasks dropped because their DA layer's queue was full"
+    );
+    describe_gauge!(
+        "task_queue_depth",
+        "Number of assigned tasks waiting for a slot, by DA layer"
+    );
+    describe_gauge!(
+        "tasks_running",
+        "Number of assigned tasks being posted, by DA layer"
+    );
+    describe_counter!(
+        "operator_bond_top_up",
+        "Counts the number of automatic operator bond top-ups"
+    );
+    describe_gauge!("operator_jailed", "Indicates if the operator is jailed");
+    describe_gauge!("operator_bond", "Operator bond in wei");
+    describe_gauge!("operator_min_bond", "Minimum operator bond in wei");
+    describe_gauge!(
+        "operator_vault_stake",
+        "Assets staked in each of the operator's vaults, in base units"
+    );
+    describe_gauge!(
+        "operator_stake_usd",
+        "USD value of the operator's priced stake"
+    );
+    describe_gauge!(
+        "operator_stake_eth",
+        "ETH value of the operator's priced stake"
+    );
+    describe_counter!(
+        "slashing_event",
+        "Counts the number of slashing events concerning our vaults, by event"
+    );
+    describe_counter!(
+        "slashing_alert_error",
+        "Counts the number of slashing alerts that could not be delivered"
+    );
+    describe_gauge!(
+        "vault_queued_for_slashing",
+        "Indicates if each of the operator's vaults is queued for slashing"
+    );
+    describe_counter!(
+        "watchtower_receipt_checked",
+        "Counts the number of receipts of other operators checked by the watchtower, by result"
+    );
+    describe_counter!(
+        "watchtower_challenge_created",
+        "Counts the number of challenges the watchtower opened"
+    );
+    describe_counter!(
+        "watchtower_challenge_error",
+        "Counts the number of challenges the watchtower failed to open"
+    );
+    describe_counter!(
+        "challenge_settled",
+        "Counts the number of challenges we were part of that were finished, by role and outcome"
+    );
+    describe_counter!(
+        "challenge_settlement_error",
+        "Counts the number of failed attempts to finish a challenge"
+    );
     describe_gauge!(
         "socket_io_connected",
-        "Indicates if the socket io connection to the aggregator is established"
+        "Indicates if the socket io connection to each aggregator is established"
     );
 
     if !config.operator.is_registered().await? {
@@ -106,51 +255,155 @@ pub async fn run<T: Transport + Clone, P: Provider<T> + Clone + 'static>(
         tracing::info!("Stake: {}", serde_json::to_string_pretty(&stake)?);
     }
 
+    let journal = Arc::new(Journal::open(&config.journal_path).await?);
+    resubmit_pending_receipts(
+        &config.kuda_instance,
+        &config.da_registry,
+        config.operator.operator_address,
+        &journal,
+    )
+    .await;
+
     let cancellation_token = CancellationToken::new();
     let socket_io_cancel = cancellation_token.clone();
+    let challenge_responder_cancel = cancellation_token.clone();
+
+    let cost_estimator = Arc::new(CostEstimator::new(
+        config.da_registry.clone(),
+        config.price_source.clone(),
+        config.operator.provider.clone(),
+    ));
+
+    let operator_monitor = OperatorMonitor {
+        operator: config.operator.clone(),
+        poll_interval: config.operator_monitor_interval,
+        bond_top_up: config.bond_top_up,
+        price_source: config.price_source.clone(),
+        status: Default::default(),
+    };
+    let operator_status = operator_monitor.status.clone();
+    let operator_monitor_cancel = cancellation_token.clone();
+    let operator_monitor_task =
+        tokio::spawn(async move { operator_monitor.run(operator_monitor_cancel).await });
+
+    let challenge_responder = ChallengeResponder {
+        operator_address: config.operator.operator_address,
+        kuda_instance: config.kuda_instance.clone(),
+        da_registry: config.da_registry.clone(),
+        journal: journal.clone(),
+        cursor: journal.cursor("challenge"),
+        poll_interval: config.challenge_poll_interval,
+    };
+    let challenge_responder_task = tokio::spawn(async move {
+        while let Err(e) = challenge_responder
+            .run(challenge_responder_cancel.clone())
+            .await
+        {
+            tracing::error!("Challenge responder error: {e:?}");
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+    });
+
+    let settlement_keeper = SettlementKeeper {
+        kuda_instance: config.kuda_instance.clone(),
+        journal: journal.clone(),
+        poll_interval: config.challenge_poll_interval,
+    };
+    let settlement_keeper_cancel = cancellation_token.clone();
+    let settlement_keeper_task = tokio::spawn(async move {
+        while let Err(e) = settlement_keeper
+            .run(settlement_keeper_cancel.clone())
+            .await
+        {
+            tracing::error!("Settlement keeper error: {e:?}");
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        }
+    });
+
+    let watchtower_task = config.watchtower_poll_interval.map(|poll_interval| {
+        let watchtower = Watchtower::new(
+            config.operator.operator_address,
+            config.kuda_instance.clone(),
+            config.da_registry.clone(),
+            journal.clone(),
+            poll_interval,
+        );
+        let watchtower_cancel = cancellation_token.clone();
+        tokio::spawn(async move {
+            while let Err(e) = watchtower.run(watchtower_cancel.clone()).await {
+                tracing::error!("Watchtower error: {e:?}");
+                tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            }
+        })
+    });
 
-    let socket_url = config.aggregator_url.clone();
-    let is_connected = Arc::new(tokio::sync::RwLock::new(false));
-    let is_connected_clone = is_connected.clone();
-    let socket_io_task = tokio::spawn(async move {
-        tokio::select! {
-            biased;
-            _ = async {
-                while let Err(e) = socket_io(
-                    socket_url.clone(),
-                    config.celestia_client.clone(),
-                    config.eip4844_client.clone(),
-                    config.operator_signer.clone(),
-                    config.kuda_instance.clone(),
-                    socket_io_cancel.clone(),
-                    is_connected_clone.clone(),
-                )
-                .await
-                {
-                    tracing::error!("Socket IO connection error: {e:?}");
-                    *is_connected_clone.write().await = false;
-                    gauge!("socket_io_connected").set(0);
-                    tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
-                }
-            } => {},
-            _ = socket_io_cancel.cancelled() => {
-                tracing::info!("Socket IO task cancelled");
-            },
+    let slashing_watcher = SlashingWatcher::new(
+        config.operator.clone(),
+        SlashingLog::open(&config.slashing_log_path).await?,
+        config.slashing_alert_webhook,
+        config.slashing_poll_interval,
+        BlockCursor::new(config.slashing_log_path.with_extension("cursor")),
+    );
+    let slashing_watcher_cancel = cancellation_token.clone();
+    let slashing_watcher_task = tokio::spawn(async move {
+        while let Err(e) = slashing_watcher.run(slashing_watcher_cancel.clone()).await {
+            tracing::error!("Slashing watcher error: {e:?}");
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
         }
     });
 
+    let task_context = Arc::new(TaskContext {
+        da_registry: config.da_registry.clone(),
+        operator_signer: config.operator_signer.clone(),
+        kuda_instance: config.kuda_instance.clone(),
+        journal: journal.clone(),
+        bidding_policy: config.bidding_policy.clone(),
+        cost_estimator,
+        retry_config: config.retry_config.clone(),
+        verify_posts: config.verify_posts,
+        executor: TaskExecutor::new(&config.executor_config),
+        dedup: TaskDeduplicator::default(),
+        aggregator_signer: config.aggregator_signer,
+    });
+
+    let aggregators = Arc::new(Aggregators::new(
+        config.aggregator_urls,
+        config.aggregator_mode,
+    ));
+    let listeners = match aggregators.mode {
+        AggregatorMode::All => (0..aggregators.aggregators.len())
+            .map(|index| vec![index])
+            .collect(),
+        AggregatorMode::Failover => vec![(0..aggregators.aggregators.len()).collect()],
+    };
+    let socket_io_tasks = listeners
+        .into_iter()
+        .map(|indices: Vec<usize>| {
+            tokio::spawn(listen_to_aggregators(
+                aggregators.clone(),
+                indices,
+                task_context.clone(),
+                socket_io_cancel.clone(),
+            ))
+        })
+        .collect::<Vec<_>>();
+
     let governor_config = Arc::new(GovernorConfig::default());
-    let app = crate::routes(is_connected.clone())
-        .layer(
-            TraceLayer::new_for_http()
-                .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
-                .on_request(trace::DefaultOnRequest::new().level(Level::INFO))
-                .on_response(trace::DefaultOnResponse::new().level(Level::INFO))
-                .on_failure(trace::DefaultOnFailure::new().level(Level::ERROR)),
-        )
-        .layer(ServiceBuilder::new().layer(GovernorLayer {
-            config: governor_config.clone(),
-        }));
+    let app = crate::routes(HealthState {
+        aggregators: aggregators.clone(),
+        operator_status,
+        da_registry: config.da_registry.clone(),
+    })
+    .layer(
+        TraceLayer::new_for_http()
+            .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
+            .on_request(trace::DefaultOnRequest::new().level(Level::INFO))
+            .on_response(trace::DefaultOnResponse::new().level(Level::INFO))
+            .on_failure(trace::DefaultOnFailure::new().level(Level::ERROR)),
+    )
+    .layer(ServiceBuilder::new().layer(GovernorLayer {
+        config: governor_config.clone(),
+    }));
 
     let listener = TcpListener::bind((config.host, config.port)).await?;
 
@@ -162,11 +415,49 @@ pub async fn run<T: Transport + Clone, P: Provider<T> + Clone + 'static>(
     .await?;
 
     cancellation_token.cancel();
-    let _ = socket_io_task.await;
+    for socket_io_task in socket_io_tasks {
+        let _ = socket_io_task.await;
+    }
+    let _ = challenge_responder_task.await;
+    let _ = operator_monitor_task.await;
+    let _ = settlement_keeper_task.await;
+    let _ = slashing_watcher_task.await;
+    if let Some(watchtower_task) = watchtower_task {
+        let _ = watchtower_task.await;
+    }
 
     Ok(())
 }
 
+/// Listens to the aggregators at `indices`, moving on to the next one whenever a connection fails
+async fn listen_to_aggregators<T: Transport + Clone, P: Provider<T> + 'static>(
+    aggregators: Arc<Aggregators>,
+    indices: Vec<usize>,
+    task_context: Arc<TaskContext<T, P>>,
+    cancellation_token: CancellationToken,
+) {
+    for index in indices.iter().cycle() {
+        let aggregator = &aggregators.aggregators[*index];
+        let result = socket_io(
+            aggregator.url.clone(),
+            task_context.clone(),
+            cancellation_token.clone(),
+            aggregator.connected.clone(),
+        )
+        .await;
+        if cancellation_token.is_cancelled() {
+            tracing::info!("Socket IO task cancelled");
+            break;
+        }
+        if let Err(e) = result {
+            tracing::error!("Socket IO connection error with {}: {e:?}", aggregator.url);
+        }
+        *aggregator.connected.write().await = false;
+        gauge!("socket_io_connected", "aggregator" => aggregator.url.to_string()).set(0);
+        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+    }
+}
+
 async fn shutdown_signal() {
     let ctrl_c = async {
         signal::ctrl_c()
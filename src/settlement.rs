@@ -0,0 +1,220 @@
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{FixedBytes, B256, U256},
+    providers::Provider,
+    transports::Transport,
+};
+use eyre::OptionExt;
+use metrics::counter;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    contracts::kuda::Kuda::{KudaErrors, KudaInstance},
+    journal::{Journal, TaskEvent, TaskRecord, TaskStage},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeRole {
+    /// The challenge was raised against one of our receipts
+    Operator,
+    /// We raised the challenge against another operator
+    Challenger,
+}
+
+impl ChallengeRole {
+    fn of(task: &TaskRecord) -> Self {
+        match task.stage {
+            TaskStage::ChallengeOpened => ChallengeRole::Challenger,
+            _ => ChallengeRole::Operator,
+        }
+    }
+
+    /// Whether we won a challenge that was, or was not, countered. A counter challenge proves
+    /// inclusion, so it decides the challenge for the operator
+    pub fn won(&self, countered: bool) -> bool {
+        match self {
+            ChallengeRole::Operator => countered,
+            ChallengeRole::Challenger => !countered,
+        }
+    }
+}
+
+impl Display for ChallengeRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChallengeRole::Operator => write!(f, "operator"),
+            ChallengeRole::Challenger => write!(f, "challenger"),
+        }
+    }
+}
+
+/// Calls `finishChallenge` on the challenges we are part of once `CHALLENGE_PERIOD` is over,
+/// since a dispute only resolves when someone does
+pub struct SettlementKeeper<T: Transport + Clone, P: Provider<T>> {
+    pub kuda_instance: Arc<KudaInstance<T, P>>,
+    pub journal: Arc<Journal>,
+    pub poll_interval: Duration,
+}
+
+impl<T: Transport + Clone, P: Provider<T>> SettlementKeeper<T, P> {
+    pub async fn run(&self, cancellation_token: CancellationToken) -> eyre::Result<()> {
+        let challenge_period = self.kuda_instance.CHALLENGE_PERIOD().call().await?._0;
+
+        loop {
+            if let Err(e) = self.poll(challenge_period).await {
+                tracing::error!("Settlement keeper error: {e:?}");
+            }
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    tracing::info!("Settlement keeper cancelled");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    async fn poll(&self, challenge_period: U256) -> eyre::Result<()> {
+        let open_challenges = self.journal.open_challenges().await;
+        if open_challenges.is_empty() {
+            return Ok(());
+        }
+
+        let latest_block = self
+            .kuda_instance
+            .provider()
+            .get_block_by_number(BlockNumberOrTag::Latest, false)
+            .await?
+            .ok_or_eyre("Could not get latest block")?;
+        let now = U256::from(latest_block.header.timestamp);
+
+        for (task_id, task) in open_challenges {
+            if let Err(e) = self.settle(task_id, &task, now, challenge_period).await {
+                counter!("challenge_settlement_error").increment(1);
+                tracing::error!("Failed to settle challenge of task {task_id}: {e:?}");
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self, task))]
+    async fn settle(
+        &self,
+        task_id: Uuid,
+        task: &TaskRecord,
+        now: U256,
+        challenge_period: U256,
+    ) -> eyre::Result<()> {
+        let task_id_bytes = FixedBytes::from(task_id.as_bytes());
+        let challenge = self
+            .kuda_instance
+            .challengeData(task_id_bytes)
+            .call()
+            .await?;
+        if now < challenge.timeStamp + challenge_period {
+            return Ok(());
+        }
+
+        let role = ChallengeRole::of(task);
+        let finish = self.kuda_instance.finishChallenge(task_id_bytes);
+        if let Err(alloy::contract::Error::TransportError(e)) = finish.call().await {
+            let error = e
+                .as_error_resp()
+                .and_then(|payload| payload.as_decoded_error::<KudaErrors>(true));
+            return match error {
+                Some(KudaErrors::ChallengeAlreadyFinalized(_)) => {
+                    tracing::info!("Challenge was finished by someone else");
+                    self.record_outcome(task_id, task, role).await
+                }
+                Some(KudaErrors::ChallengePeriodNotOver(_))
+                | Some(KudaErrors::CounterChallengePeriodNotOver(_)) => Ok(()),
+                _ => Err(e.into()),
+            };
+        }
+
+        let tx_receipt = finish.send().await?.get_receipt().await?;
+        if !tx_receipt.status() {
+            return Err(eyre::eyre!(
+                "finishChallenge transaction {} reverted",
+                tx_receipt.transaction_hash
+            ));
+        }
+        tracing::info!(
+            "Finished challenge with tx hash: {}",
+            tx_receipt.transaction_hash
+        );
+        self.record_outcome(task_id, task, role).await
+    }
+
+    async fn record_outcome(
+        &self,
+        task_id: Uuid,
+        task: &TaskRecord,
+        role: ChallengeRole,
+    ) -> eyre::Result<()> {
+        let countered = match role {
+            ChallengeRole::Operator => task.stage == TaskStage::CounterChallenged,
+            ChallengeRole::Challenger => self.was_countered(task_id, task).await?,
+        };
+        let outcome = if role.won(countered) { "won" } else { "lost" };
+        counter!("challenge_settled", "role" => role.to_string(), "outcome" => outcome)
+            .increment(1);
+        if role.won(countered) {
+            tracing::info!("Challenge settled, {outcome} as {role}");
+        } else {
+            tracing::warn!("Challenge settled, {outcome} as {role}");
+        }
+        self.journal.record(task_id, TaskEvent::Settled).await
+    }
+
+    /// Whether the operator we challenged countered our challenge
+    async fn was_countered(&self, task_id: Uuid, task: &TaskRecord) -> eyre::Result<bool> {
+        let from_block = match task.challenge_tx_hash {
+            Some(tx_hash) => self
+                .kuda_instance
+                .provider()
+                .get_transaction_receipt(tx_hash)
+                .await?
+                .and_then(|receipt| receipt.block_number)
+                .map(BlockNumberOrTag::Number)
+                .unwrap_or(BlockNumberOrTag::Earliest),
+            None => BlockNumberOrTag::Earliest,
+        };
+        let counter_challenges = self
+            .kuda_instance
+            .CounterChallengeCreated_filter()
+            .topic1(B256::right_padding_from(task_id.as_bytes()))
+            .from_block(from_block)
+            .query()
+            .await?;
+        Ok(!counter_challenges.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::TxHash;
+
+    use super::*;
+
+    #[test]
+    fn test_challenge_outcome() {
+        let mut task = TaskRecord {
+            stage: TaskStage::Challenged,
+            ..Default::default()
+        };
+        assert_eq!(ChallengeRole::of(&task), ChallengeRole::Operator);
+        assert!(!ChallengeRole::Operator.won(false));
+        assert!(ChallengeRole::Operator.won(true));
+
+        task.stage = TaskStage::ChallengeOpened;
+        task.challenge_tx_hash = Some(TxHash::ZERO);
+        assert_eq!(ChallengeRole::of(&task), ChallengeRole::Challenger);
+        assert!(ChallengeRole::Challenger.won(false));
+        assert!(!ChallengeRole::Challenger.won(true));
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::Path,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, TxHash, U256},
+    providers::Provider,
+    rpc::types::{Filter, Log},
+    sol_types::SolEvent,
+    transports::Transport,
+};
+use metrics::{counter, gauge};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::{
+    contracts::{core::SlasherLib::QueuedSlashing, vault::Vault},
+    journal::BlockCursor,
+    operator::Operator,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum SlashingEvent {
+    /// A DSS requested slashing; it can be vetoed until Core's slashing delay has passed
+    #[serde(rename_all = "camelCase")]
+    Requested {
+        dss: Address,
+        vaults: Vec<Address>,
+        slash_percentages_wad: Vec<U256>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Finalized {
+        finisher: Address,
+        dss: Address,
+        vaults: Vec<Address>,
+        nonce: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Cancelled {
+        canceller: Address,
+        dss: Address,
+        vaults: Vec<Address>,
+        nonce: u64,
+    },
+    /// Assets were taken out of one of our vaults
+    Slashed { vault: Address, assets: U256 },
+    /// `isVaultQueuedForSlashing` changed for one of our vaults
+    #[serde(rename_all = "camelCase")]
+    QueuedStateChanged { vault: Address, queued: bool },
+}
+
+impl SlashingEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            SlashingEvent::Requested { .. } => "requested",
+            SlashingEvent::Finalized { .. } => "finalized",
+            SlashingEvent::Cancelled { .. } => "cancelled",
+            SlashingEvent::Slashed { .. } => "slashed",
+            SlashingEvent::QueuedStateChanged { .. } => "queued_state_changed",
+        }
+    }
+
+    fn from_queued(queued: &QueuedSlashing, actor: Address, finalized: bool) -> Self {
+        let dss = queued.dss;
+        let vaults = queued.vaults.clone();
+        let nonce = queued.nonce.to();
+        if finalized {
+            SlashingEvent::Finalized {
+                finisher: actor,
+                dss,
+                vaults,
+                nonce,
+            }
+        } else {
+            SlashingEvent::Cancelled {
+                canceller: actor,
+                dss,
+                vaults,
+                nonce,
+            }
+        }
+    }
+}
+
+impl Display for SlashingEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let vaults = |vaults: &[Address]| {
+            vaults
+                .iter()
+                .map(Address::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            SlashingEvent::Requested { dss, vaults: v, .. } => write!(
+                f,
+                "DSS {dss} requested slashing of vaults {}, veto it before Core's slashing delay passes",
+                vaults(v)
+            ),
+            SlashingEvent::Finalized {
+                dss,
+                vaults: v,
+                nonce,
+                ..
+            } => write!(
+                f,
+                "Slashing {nonce} of vaults {} by DSS {dss} was finalized",
+                vaults(v)
+            ),
+            SlashingEvent::Cancelled {
+                dss,
+                vaults: v,
+                nonce,
+                ..
+            } => write!(
+                f,
+                "Slashing {nonce} of vaults {} by DSS {dss} was cancelled",
+                vaults(v)
+            ),
+            SlashingEvent::Slashed { vault, assets } => {
+                write!(f, "Vault {vault} was slashed {assets} assets")
+            }
+            SlashingEvent::QueuedStateChanged { vault, queued } => {
+                if *queued {
+                    write!(f, "Vault {vault} is queued for slashing")
+                } else {
+                    write!(f, "Vault {vault} is no longer queued for slashing")
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlashingRecord {
+    pub timestamp: u64,
+    pub block_number: Option<u64>,
+    pub tx_hash: Option<TxHash>,
+    pub event: SlashingEvent,
+}
+
+/// Append-only, newline-delimited JSON log of the slashing events that concern our vaults
+pub struct SlashingLog {
+    file: Mutex<File>,
+}
+
+impl SlashingLog {
+    pub async fn open(path: &Path) -> eyre::Result<Self> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub async fn record(&self, record: &SlashingRecord) -> eyre::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().await;
+        file.write_all(&line).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+}
+
+/// Watches Core and our vaults for slashing, and alerts on everything that concerns us
+pub struct SlashingWatcher<T: Transport + Clone, P: Provider<T>> {
+    pub operator: Arc<Operator<T, P>>,
+    pub log: SlashingLog,
+    /// Receives a `{"text": <MESSAGE>}` POST for every event, e.g. a Slack incoming webhook
+    pub alert_webhook: Option<Url>,
+    pub poll_interval: Duration,
+    pub http_client: reqwest::Client,
+    /// Block to resume polling slashing events from after a restart
+    cursor: BlockCursor,
+    queued: Mutex<HashMap<Address, bool>>,
+}
+
+impl<T: Transport + Clone, P: Provider<T> + Clone> SlashingWatcher<T, P> {
+    pub fn new(
+        operator: Arc<Operator<T, P>>,
+        log: SlashingLog,
+        alert_webhook: Option<Url>,
+        poll_interval: Duration,
+        cursor: BlockCursor,
+    ) -> Self {
+        Self {
+            operator,
+            log,
+            alert_webhook,
+            poll_interval,
+            http_client: reqwest::Client::new(),
+            cursor,
+            queued: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Polls slashing events and the queued state of our vaults
+    pub async fn run(&self, cancellation_token: CancellationToken) -> eyre::Result<()> {
+        let mut from_block = match self.cursor.load().await? {
+            Some(block) => block,
+            None => self.operator.provider.get_block_number().await?,
+        };
+
+        loop {
+            match self.poll(from_block).await {
+                Ok(next_block) => from_block = next_block,
+                Err(e) => tracing::error!("Slashing watcher error: {e:?}"),
+            }
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    tracing::info!("Slashing watcher cancelled");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+        }
+    }
+
+    /// Processes the slashing events from `from_block` up to the latest block and returns the
+    /// block to resume from. Events are only reported once all of them were fetched, so that a
+    /// failed query doesn't report the others twice
+    async fn poll(&self, from_block: u64) -> eyre::Result<u64> {
+        let operator_address = self.operator.operator_address;
+        let core = &self.operator.core_instance;
+        let vaults = core
+            .getOperatorVaults(operator_address)
+            .call()
+            .await?
+            .vaults;
+
+        for vault in &vaults {
+            let queued = core.isVaultQueuedForSlashing(*vault).call().await?._0;
+            gauge!("vault_queued_for_slashing", "vault" => vault.to_string()).set(if queued {
+                1
+            } else {
+                0
+            });
+            let previous = self.queued.lock().await.insert(*vault, queued);
+            // Alert on the first poll only if the vault is already queued
+            if previous.unwrap_or(false) != queued {
+                self.report(
+                    SlashingEvent::QueuedStateChanged {
+                        vault: *vault,
+                        queued,
+                    },
+                    None,
+                )
+                .await;
+            }
+        }
+
+        let latest_block = self.operator.provider.get_block_number().await?;
+        if latest_block < from_block {
+            return Ok(from_block);
+        }
+
+        let mut events = Vec::new();
+        let requested = core
+            .RequestedSlashing_filter()
+            .from_block(from_block)
+            .to_block(latest_block)
+            .query()
+            .await?;
+        for (event, log) in requested {
+            let request = event.requestSlashing;
+            if request.operator == operator_address {
+                let event = SlashingEvent::Requested {
+                    dss: event.dss,
+                    vaults: request.vaults,
+                    slash_percentages_wad: request
+                        .slashPercentagesWad
+                        .iter()
+                        .map(|percentage| U256::from(*percentage))
+                        .collect(),
+                };
+                events.push((event, log));
+            }
+        }
+
+        let finalized = core
+            .FinalizedSlashing_filter()
+            .from_block(from_block)
+            .to_block(latest_block)
+            .query()
+            .await?;
+        for (event, log) in finalized {
+            if event.queuedSlashing.operator == operator_address {
+                let event = SlashingEvent::from_queued(&event.queuedSlashing, event.finisher, true);
+                events.push((event, log));
+            }
+        }
+
+        let cancelled = core
+            .CancelledSlashing_filter()
+            .from_block(from_block)
+            .to_block(latest_block)
+            .query()
+            .await?;
+        for (event, log) in cancelled {
+            if event.queuedSlashing.operator == operator_address {
+                let event =
+                    SlashingEvent::from_queued(&event.queuedSlashing, event.canceller, false);
+                events.push((event, log));
+            }
+        }
+
+        if !vaults.is_empty() {
+            let filter = Filter::new()
+                .address(vaults)
+                .event_signature(Vault::Slashed::SIGNATURE_HASH)
+                .from_block(from_block)
+                .to_block(latest_block);
+            for log in self.operator.provider.get_logs(&filter).await? {
+                let slashed = log.log_decode::<Vault::Slashed>()?;
+                let event = SlashingEvent::Slashed {
+                    vault: slashed.inner.address,
+                    assets: slashed.inner.data.assets,
+                };
+                events.push((event, log));
+            }
+        }
+
+        events.sort_by_key(|(_, log)| (log.block_number, log.log_index));
+        for (event, log) in events {
+            self.report(event, Some(&log)).await;
+        }
+
+        self.cursor.save(latest_block + 1).await?;
+        Ok(latest_block + 1)
+    }
+
+    /// Records, counts and alerts on `event`
+    async fn report(&self, event: SlashingEvent, log: Option<&Log>) {
+        counter!("slashing_event", "event" => event.name()).increment(1);
+        match &event {
+            SlashingEvent::Cancelled { .. }
+            | SlashingEvent::QueuedStateChanged { queued: false, .. } => {
+                tracing::warn!("SLASHING: {event}")
+            }
+            _ => tracing::error!("SLASHING: {event}"),
+        }
+
+        let record = SlashingRecord {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+            block_number: log.and_then(|log| log.block_number),
+            tx_hash: log.and_then(|log| log.transaction_hash),
+            event,
+        };
+        if let Err(e) = self.log.record(&record).await {
+            tracing::error!("Failed to record slashing event: {e:?}");
+        }
+        if let Err(e) = self.alert(&record).await {
+            counter!("slashing_alert_error").increment(1);
+            tracing::error!("Failed to send slashing alert: {e:?}");
+        }
+    }
+
+    async fn alert(&self, record: &SlashingRecord) -> eyre::Result<()> {
+        let Some(webhook) = &self.alert_webhook else {
+            return Ok(());
+        };
+        let mut text = format!(
+            "Operator {}: {}",
+            self.operator.operator_address, record.event
+        );
+        if let Some(tx_hash) = record.tx_hash {
+            text.push_str(&format!(" (tx {tx_hash})"));
+        }
+        self.http_client
+            .post(webhook.clone())
+            .json(&serde_json::json!({ "text": text }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_slashing_log() {
+        let path = std::env::temp_dir().join(format!("kuda-slashing-{}.jsonl", Uuid::new_v4()));
+        let records = [
+            SlashingRecord {
+                timestamp: 1_700_000_000,
+                block_number: Some(10),
+                tx_hash: Some(TxHash::repeat_byte(1)),
+                event: SlashingEvent::Requested {
+                    dss: Address::repeat_byte(2),
+                    vaults: vec![Address::repeat_byte(3)],
+                    slash_percentages_wad: vec![U256::from(10u64.pow(17))],
+                },
+            },
+            SlashingRecord {
+                timestamp: 1_700_000_100,
+                block_number: None,
+                tx_hash: None,
+                event: SlashingEvent::QueuedStateChanged {
+                    vault: Address::repeat_byte(3),
+                    queued: true,
+                },
+            },
+        ];
+
+        let log = SlashingLog::open(&path).await.unwrap();
+        for record in &records {
+            log.record(record).await.unwrap();
+        }
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let read: Vec<SlashingRecord> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(read, records);
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
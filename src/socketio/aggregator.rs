@@ -0,0 +1,118 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+};
+
+use clap::ValueEnum;
+use tokio::sync::{Mutex, RwLock};
+use url::Url;
+use uuid::Uuid;
+
+/// How many deliveries per event are remembered for deduplication
+const DEDUP_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AggregatorMode {
+    /// Stay connected to every aggregator at the same time
+    All,
+    /// Connect to the first reachable aggregator, in order, and fail over to the next
+    Failover,
+}
+
+pub struct Aggregator {
+    pub url: Url,
+    pub connected: Arc<RwLock<bool>>,
+}
+
+/// The aggregators we listen to and whether we are connected to each of them
+pub struct Aggregators {
+    pub mode: AggregatorMode,
+    pub aggregators: Vec<Aggregator>,
+}
+
+impl Aggregators {
+    pub fn new(urls: Vec<Url>, mode: AggregatorMode) -> Self {
+        Self {
+            mode,
+            aggregators: urls
+                .into_iter()
+                .map(|url| Aggregator {
+                    url,
+                    connected: Arc::new(RwLock::new(false)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Connection state of every aggregator, in configuration order
+    pub async fn connections(&self) -> Vec<(Url, bool)> {
+        let mut connections = Vec::with_capacity(self.aggregators.len());
+        for aggregator in &self.aggregators {
+            connections.push((aggregator.url.clone(), *aggregator.connected.read().await));
+        }
+        connections
+    }
+}
+
+/// Remembers recent deliveries so that a task sent by several aggregators is handled once
+#[derive(Default)]
+pub struct TaskDeduplicator {
+    seen: Mutex<Deliveries>,
+}
+
+#[derive(Default)]
+struct Deliveries {
+    set: HashSet<(&'static str, Uuid)>,
+    order: VecDeque<(&'static str, Uuid)>,
+}
+
+impl TaskDeduplicator {
+    /// Whether this is the first delivery of `event` for `task_id`
+    pub async fn first_delivery(&self, event: &'static str, task_id: Uuid) -> bool {
+        let mut seen = self.seen.lock().await;
+        if !seen.set.insert((event, task_id)) {
+            return false;
+        }
+        seen.order.push_back((event, task_id));
+        if seen.order.len() > DEDUP_CAPACITY {
+            if let Some(oldest) = seen.order.pop_front() {
+                seen.set.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    /// Forgets the delivery of `event` for `task_id`, so that a redelivery is handled again
+    pub async fn forget(&self, event: &'static str, task_id: Uuid) {
+        let mut seen = self.seen.lock().await;
+        if seen.set.remove(&(event, task_id)) {
+            seen.order.retain(|delivery| *delivery != (event, task_id));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_task_deduplicator() {
+        let dedup = TaskDeduplicator::default();
+        let task_id = Uuid::new_v4();
+
+        assert!(dedup.first_delivery("data-posting-intent", task_id).await);
+        assert!(!dedup.first_delivery("data-posting-intent", task_id).await);
+        assert!(dedup.first_delivery("task-responsibility", task_id).await);
+
+        dedup.forget("task-responsibility", task_id).await;
+        assert!(dedup.first_delivery("task-responsibility", task_id).await);
+        assert!(!dedup.first_delivery("task-responsibility", task_id).await);
+
+        for _ in 0..DEDUP_CAPACITY {
+            dedup
+                .first_delivery("data-posting-intent", Uuid::new_v4())
+                .await;
+        }
+        assert!(dedup.first_delivery("data-posting-intent", task_id).await);
+    }
+}
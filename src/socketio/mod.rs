@@ -1,51 +1,79 @@
 use std::{str::FromStr, sync::Arc};
 
 use alloy::{
-    primitives::{Address, Bytes, FixedBytes},
+    primitives::{Address, Bytes, FixedBytes, TxHash},
     providers::Provider,
     signers::{Signature, Signer},
-    sol_types::SolValue,
     transports::Transport,
 };
+use eyre::WrapErr;
 use futures_util::FutureExt;
 use metrics::{counter, gauge};
-use model::{DaLayer, Ping, Pong, PostingIntent, PostingInterest, TaskResponsibility};
+use model::{Ping, Pong, PostingIntent, PostingInterest, TaskReceipt, TaskResponsibility};
 use rust_socketio::{asynchronous::ClientBuilder, Payload, TransportType};
 use serde_json::json;
 use tokio::sync::RwLock;
 use tokio_util::sync::CancellationToken;
 use url::Url;
+use uuid::Uuid;
+
+use aggregator::TaskDeduplicator;
+use signature::{verify_aggregator_signature, AggregatorSigner};
 
 use crate::{
+    bidding::{Bid, BiddingPolicy},
     contracts::kuda::Kuda::KudaInstance,
-    da::{celestia::CelestiaClient, eip4844::Eip4844Client, BlobData, Submitter},
+    cost::CostEstimator,
+    da::{registry::DaRegistry, BlobData, DaReceipt},
+    executor::TaskExecutor,
+    journal::{Journal, TaskAssignment, TaskEvent},
     kms::KmsSigner,
+    retry::{retry, RetryConfig},
 };
 
+pub mod aggregator;
 pub mod model;
+pub mod signature;
+
+/// Everything the Socket.IO event handlers need to bid on and post tasks
+pub struct TaskContext<T: Transport + Clone, P: Provider<T>> {
+    pub da_registry: Arc<DaRegistry>,
+    pub operator_signer: Arc<dyn KmsSigner + Send + Sync + 'static>,
+    pub kuda_instance: Arc<KudaInstance<T, P>>,
+    pub journal: Arc<Journal>,
+    pub bidding_policy: Arc<dyn BiddingPolicy>,
+    pub cost_estimator: Arc<CostEstimator<T, P>>,
+    pub retry_config: RetryConfig,
+    /// Read every posted blob back before submitting its receipt
+    pub verify_posts: bool,
+    pub executor: TaskExecutor,
+    pub dedup: TaskDeduplicator,
+    pub aggregator_signer: AggregatorSigner,
+}
 
 pub async fn socket_io<T: Transport + Clone, P: Provider<T> + 'static>(
     socket_url: Url,
-    celestia_client: Arc<CelestiaClient>,
-    eip4844_client: Arc<Eip4844Client>,
-    operator_signer: Arc<dyn KmsSigner + Send + Sync + 'static>,
-    kuda_instance: Arc<KudaInstance<T, P>>,
+    context: Arc<TaskContext<T, P>>,
     cancellation_token: CancellationToken,
     is_connected: Arc<RwLock<bool>>,
 ) -> eyre::Result<()> {
-    let operator_address = Signer::address(&*operator_signer);
+    let operator_address = Signer::address(&*context.operator_signer);
     let message = "connection";
     let signature = hex::encode(
-        operator_signer
+        context
+            .operator_signer
             .sign_message(message.as_bytes())
             .await?
             .as_bytes(),
     );
 
+    let aggregator = socket_url.to_string();
     let connected_on_connect = is_connected.clone();
     let connected_on_disconnect = is_connected.clone();
+    let aggregator_on_connect = aggregator.clone();
+    let aggregator_on_disconnect = aggregator.clone();
 
-    let kuda_instance_posting_intent = kuda_instance.clone();
+    let context_posting_intent = context.clone();
     let builder = ClientBuilder::new(socket_url.clone())
         .transport_type(TransportType::Websocket)
         .namespace("/")
@@ -56,19 +84,21 @@ pub async fn socket_io<T: Transport + Clone, P: Provider<T> + 'static>(
         .reconnect_on_disconnect(true)
         .on(rust_socketio::Event::Connect, move |_, _| {
             let is_connected = connected_on_connect.clone();
+            let aggregator = aggregator_on_connect.clone();
             async move {
                 *is_connected.write().await = true;
-                gauge!("socket_io_connected").set(1);
-                tracing::info!("Connected to server");
+                gauge!("socket_io_connected", "aggregator" => aggregator.clone()).set(1);
+                tracing::info!("Connected to aggregator {aggregator}");
             }
             .boxed()
         })
         .on(rust_socketio::Event::Close, move |_, _| {
             let is_connected = connected_on_disconnect.clone();
+            let aggregator = aggregator_on_disconnect.clone();
             async move {
                 *is_connected.write().await = false;
-                gauge!("socket_io_connected").set(0);
-                tracing::info!("Disconnected from server");
+                gauge!("socket_io_connected", "aggregator" => aggregator.clone()).set(0);
+                tracing::info!("Disconnected from aggregator {aggregator}");
             }
             .boxed()
         })
@@ -90,39 +120,22 @@ pub async fn socket_io<T: Transport + Clone, P: Provider<T> + 'static>(
         })
         .on("data-posting-intent", move |payload, client| {
             let operator_address = operator_address;
-            let kuda_instance = kuda_instance_posting_intent.clone();
+            let context = context_posting_intent.clone();
             async move {
                 let result =
-                    process_posting_intent(&payload, &client, &operator_address, &kuda_instance)
-                        .await;
+                    process_posting_intent(&payload, &client, &operator_address, &context).await;
                 if let Err(e) = result {
                     tracing::error!("Posting intent error: {e:?}");
                 }
             }
             .boxed()
         })
-        .on("task-responsibility", move |payload, _| {
-            let celestia_client = celestia_client.clone();
-            let eip4844_client = eip4844_client.clone();
-            let kuda_instance = kuda_instance.clone();
-            let operator_signer = operator_signer.clone();
+        .on("task-responsibility", move |payload, client| {
+            let context = context.clone();
             async move {
-                let result = process_task_responsibility(
-                    payload,
-                    &celestia_client,
-                    &eip4844_client,
-                    operator_signer.clone(),
-                    &kuda_instance,
-                )
-                .await;
-                match result {
-                    Ok(_) => {
-                        counter!("task_responsibility_success").increment(1);
-                    }
-                    Err(e) => {
-                        counter!("task_responsibility_error").increment(1);
-                        tracing::error!("Task responsibility error: {e:?}");
-                    }
+                if let Err(e) = process_task_responsibility(payload, client, context).await {
+                    counter!("task_responsibility_error").increment(1);
+                    tracing::error!("Task responsibility error: {e:?}");
                 }
             }
             .boxed()
@@ -143,8 +156,8 @@ pub async fn socket_io<T: Transport + Clone, P: Provider<T> + 'static>(
                 }
             }
         } => {
-            gauge!("socket_io_connected").set(0);
-            tracing::info!("Socket IO disconnected");
+            gauge!("socket_io_connected", "aggregator" => aggregator.clone()).set(0);
+            tracing::info!("Socket IO disconnected from {aggregator}");
             return Err(eyre::eyre!("Socket IO disconnected"));
         }
     }
@@ -175,93 +188,643 @@ async fn process_ping(
     Ok(())
 }
 
-#[tracing::instrument(skip(client, operator_address, kuda_instance))]
+#[tracing::instrument(skip(client, operator_address, context))]
 async fn process_posting_intent<T: Transport + Clone, P: Provider<T>>(
     payload: &Payload,
     client: &rust_socketio::asynchronous::Client,
     operator_address: &Address,
-    kuda_instance: &KudaInstance<T, P>,
+    context: &TaskContext<T, P>,
 ) -> eyre::Result<()> {
     if let Payload::Text(values) = payload {
         let posting_intent = serde_json::from_value::<PostingIntent>(values[0].clone())?;
-        tracing::info!("Received task id: {}", posting_intent.task_id);
-        counter!("posting_intent").increment(1);
-        // TODO: add custom logic to determine if we want to post data
-        let client_balance = kuda_instance
-            .kudaAccount(posting_intent.client_address, posting_intent.reward_token)
-            .call()
-            .await?
-            .balance;
-        if client_balance >= posting_intent.reward_amount {
-            let posting_interest = PostingInterest {
-                task_id: posting_intent.task_id,
-                operator_address: *operator_address,
-                da_layer: posting_intent.acceptable_da_layers[0],
-            };
-            client
-                .emit(
-                    "data-posting-interest",
-                    serde_json::to_value(posting_interest)?,
-                )
-                .await?;
-        } else {
-            tracing::error!(
-                "Client balance: {} is less than reward amount: {}",
-                client_balance,
-                posting_intent.reward_amount
-            );
+        let task_id = posting_intent.task_id;
+        if !context
+            .dedup
+            .first_delivery("data-posting-intent", task_id)
+            .await
+        {
+            tracing::debug!("Ignoring duplicate posting intent {task_id}");
+            return Ok(());
+        }
+        let result = bid(posting_intent, client, operator_address, context).await;
+        if result.is_err() {
+            // Let a redelivery be bid on
+            context.dedup.forget("data-posting-intent", task_id).await;
+        }
+        return result;
+    }
+    Ok(())
+}
+
+/// Expresses interest in a posting intent if the bidding policy accepts it
+async fn bid<T: Transport + Clone, P: Provider<T>>(
+    mut posting_intent: PostingIntent,
+    client: &rust_socketio::asynchronous::Client,
+    operator_address: &Address,
+    context: &TaskContext<T, P>,
+) -> eyre::Result<()> {
+    tracing::info!("Received task id: {}", posting_intent.task_id);
+    counter!("posting_intent").increment(1);
+    context
+        .journal
+        .record_or_log(posting_intent.task_id, TaskEvent::from(&posting_intent))
+        .await;
+
+    // Only bid on DA layers we have room to post to
+    posting_intent
+        .acceptable_da_layers
+        .retain(|da_layer| context.executor.has_capacity(*da_layer));
+    if posting_intent.acceptable_da_layers.is_empty() {
+        counter!("posting_intent_rejected").increment(1);
+        context
+            .dedup
+            .forget("data-posting-intent", posting_intent.task_id)
+            .await;
+        tracing::info!(
+            "Not bidding on task {}: at capacity",
+            posting_intent.task_id
+        );
+        return Ok(());
+    }
+
+    let costs = context.cost_estimator.estimate(&posting_intent).await;
+    let da_layer = match context.bidding_policy.bid(&posting_intent, &costs) {
+        Bid::Accept(da_layer) => da_layer,
+        Bid::Reject(reason) => {
+            counter!("posting_intent_rejected").increment(1);
+            tracing::info!("Not bidding on task {}: {reason}", posting_intent.task_id);
+            return Ok(());
         }
+    };
+    let client_balance = context
+        .kuda_instance
+        .kudaAccount(posting_intent.client_address, posting_intent.reward_token)
+        .call()
+        .await?
+        .balance;
+    if client_balance >= posting_intent.reward_amount {
+        let posting_interest = PostingInterest {
+            task_id: posting_intent.task_id,
+            operator_address: *operator_address,
+            da_layer,
+        };
+        client
+            .emit(
+                "data-posting-interest",
+                serde_json::to_value(&posting_interest)?,
+            )
+            .await?;
+        context
+            .journal
+            .record_or_log(
+                posting_interest.task_id,
+                TaskEvent::InterestSent {
+                    da_layer: posting_interest.da_layer,
+                },
+            )
+            .await;
+    } else {
+        tracing::error!(
+            "Client balance: {} is less than reward amount: {}",
+            client_balance,
+            posting_intent.reward_amount
+        );
     }
     Ok(())
 }
 
-#[tracing::instrument(skip(celestia_client, eip4844_client, operator_signer, kuda_instance))]
-async fn process_task_responsibility<T: Transport + Clone, P: Provider<T>>(
+/// Queues a task on the executor of its DA layer, answering repeated deliveries with the receipt
+/// we already submitted
+#[tracing::instrument(skip(client, context))]
+async fn process_task_responsibility<T: Transport + Clone, P: Provider<T> + 'static>(
     payload: Payload,
-    celestia_client: &CelestiaClient,
-    eip4844_client: &Eip4844Client,
-    operator_signer: Arc<dyn KmsSigner + Send + Sync + 'static>,
-    kuda_instance: &KudaInstance<T, P>,
+    client: rust_socketio::asynchronous::Client,
+    context: Arc<TaskContext<T, P>>,
 ) -> eyre::Result<()> {
     if let Payload::Text(values) = payload {
         let task = serde_json::from_value::<TaskResponsibility>(values[0].clone())?;
-        tracing::info!("Received task-responsibility: {}", task.task_id);
-        counter!("task_responsibility").increment(1);
-        let blob_data = BlobData::from_str(&task.data)?;
-        let context = match task.da_layer {
-            DaLayer::Celestia => {
-                let receipt = celestia_client.submit(&task.commitment, blob_data).await?;
-                Bytes::copy_from_slice(&(receipt.namespace.0, receipt.height).abi_encode())
+        let task_id = task.task_id;
+        if !context
+            .dedup
+            .first_delivery("task-responsibility", task_id)
+            .await
+        {
+            tracing::debug!("Answering duplicate task-responsibility {task_id}");
+            return send_receipt(&client, &context, task_id).await;
+        }
+        let result = queue_task(task, client, context.clone()).await;
+        if result.is_err() {
+            // Let a redelivery be queued
+            context.dedup.forget("task-responsibility", task_id).await;
+        }
+        return result;
+    }
+    Ok(())
+}
+
+/// Checks an assigned task and queues it on the executor, forgetting its delivery if posting
+/// it fails
+async fn queue_task<T: Transport + Clone, P: Provider<T> + 'static>(
+    task: TaskResponsibility,
+    client: rust_socketio::asynchronous::Client,
+    context: Arc<TaskContext<T, P>>,
+) -> eyre::Result<()> {
+    tracing::info!("Received task-responsibility: {}", task.task_id);
+    counter!("task_responsibility").increment(1);
+    let task_id = task.task_id;
+    let operator_address = Signer::address(&*context.operator_signer);
+    let assignment = TaskAssignment::from(&task);
+    let da_receipt = match prior_submission(
+        &context.kuda_instance,
+        &context.journal,
+        task_id,
+        operator_address,
+    )
+    .await?
+    {
+        Some(PriorSubmission::Complete) => {
+            counter!("task_responsibility_duplicate").increment(1);
+            tracing::info!("Task {task_id} was already completed, not posting it again");
+            return send_receipt(&client, &context, task_id).await;
+        }
+        Some(PriorSubmission::BlobPosted(da_receipt)) => {
+            counter!("task_responsibility_duplicate").increment(1);
+            tracing::info!("Blob of task {task_id} was already posted, reusing its receipt");
+            Some(da_receipt)
+        }
+        None => {
+            context
+                .journal
+                .record_or_log(task_id, TaskEvent::Assigned(assignment.clone()))
+                .await;
+            None
+        }
+    };
+
+    if let Err(e) = verify_aggregator_signature(
+        &task,
+        operator_address,
+        context.aggregator_signer,
+        &context.kuda_instance,
+    )
+    .await
+    {
+        counter!("task_signature_invalid").increment(1);
+        context
+            .journal
+            .record_or_log(
+                task_id,
+                TaskEvent::Failed {
+                    error: e.to_string(),
+                },
+            )
+            .await;
+        return Err(e);
+    }
+
+    let result = context
+        .executor
+        .spawn(task.da_layer, {
+            let context = context.clone();
+            async move {
+                match post_task(&context, task, assignment, da_receipt).await {
+                    Ok(_) => {
+                        counter!("task_responsibility_success").increment(1);
+                        if let Err(e) = send_receipt(&client, &context, task_id).await {
+                            tracing::error!("Could not send receipt of task {task_id}: {e:?}");
+                        }
+                    }
+                    Err(e) => {
+                        counter!("task_responsibility_error").increment(1);
+                        tracing::error!("Task {task_id} error: {e:?}");
+                        context.dedup.forget("task-responsibility", task_id).await;
+                    }
+                }
             }
-            DaLayer::Eip4844 => {
-                let receipt = eip4844_client.submit(&task.commitment, blob_data).await?;
-                Bytes::copy_from_slice(&receipt.beacon_block_slot.abi_encode())
+        })
+        .await;
+    if let Err(e) = result {
+        context
+            .journal
+            .record_or_log(
+                task_id,
+                TaskEvent::Failed {
+                    error: e.to_string(),
+                },
+            )
+            .await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Posts a task's blob to its DA layer and submits the receipt on-chain
+#[tracing::instrument(skip_all, fields(task_id = %task.task_id))]
+async fn post_task<T: Transport + Clone, P: Provider<T>>(
+    context: &TaskContext<T, P>,
+    task: TaskResponsibility,
+    assignment: TaskAssignment,
+    da_receipt: Option<DaReceipt>,
+) -> eyre::Result<()> {
+    let journal = &context.journal;
+    let result = async {
+        let da_receipt = match da_receipt {
+            Some(da_receipt) => da_receipt,
+            None => {
+                preflight(context, &task).await?;
+                post_blob(context, &task).await?
             }
         };
-        let signature = Signature::from_str(&task.signature)?;
 
+        submit_receipt(
+            &context.kuda_instance,
+            &context.da_registry,
+            Signer::address(&*context.operator_signer),
+            task.task_id,
+            &assignment,
+            &da_receipt,
+        )
+        .await
+    }
+    .await;
+
+    match result {
+        Ok(tx_hash) => {
+            journal
+                .record_or_log(task.task_id, TaskEvent::ReceiptMined { tx_hash })
+                .await;
+            Ok(())
+        }
+        Err(e) => {
+            journal
+                .record_or_log(
+                    task.task_id,
+                    TaskEvent::Failed {
+                        error: e.to_string(),
+                    },
+                )
+                .await;
+            Err(e)
+        }
+    }
+}
+
+/// Sends the receipt we submitted for a task back to the aggregator, if there is one yet
+async fn send_receipt<T: Transport + Clone, P: Provider<T>>(
+    client: &rust_socketio::asynchronous::Client,
+    context: &TaskContext<T, P>,
+    task_id: Uuid,
+) -> eyre::Result<()> {
+    let task_receipt = journaled_receipt(
+        &context.journal,
+        &context.da_registry,
+        Signer::address(&*context.operator_signer),
+        task_id,
+    )
+    .await?;
+    let Some(task_receipt) = task_receipt else {
+        tracing::debug!("No receipt of task {task_id} to send yet");
+        return Ok(());
+    };
+    client
+        .emit("task-receipt", serde_json::to_value(&task_receipt)?)
+        .await?;
+    counter!("task_receipt_sent").increment(1);
+    Ok(())
+}
+
+/// The receipt journaled for a task, once it was submitted on-chain
+async fn journaled_receipt(
+    journal: &Journal,
+    da_registry: &DaRegistry,
+    operator_address: Address,
+    task_id: Uuid,
+) -> eyre::Result<Option<TaskReceipt>> {
+    let Some(record) = journal.task(&task_id).await else {
+        return Ok(None);
+    };
+    if !record.is_receipt_submitted() {
+        return Ok(None);
+    }
+    let (Some(assignment), Some(da_receipt)) = (record.assignment, record.receipt) else {
+        return Ok(None);
+    };
+    let context = da_registry.backend_for(&da_receipt)?.context(&da_receipt)?;
+    Ok(Some(TaskReceipt {
+        task_id,
+        operator_address,
+        da_layer: assignment.da_layer,
+        commitment: assignment.commitment,
+        context,
+        tx_hash: record.receipt_tx_hash,
+    }))
+}
+
+/// Checks, right before the costly DA post, that posting the task can still earn its reward
+async fn preflight<T: Transport + Clone, P: Provider<T>>(
+    context: &TaskContext<T, P>,
+    task: &TaskResponsibility,
+) -> eyre::Result<()> {
+    let operator_address = Signer::address(&*context.operator_signer);
+    let kuda_instance = &context.kuda_instance;
+
+    let task_operator = kuda_instance
+        .taskOperator(FixedBytes::from(task.task_id.as_bytes()))
+        .call()
+        .await?
+        .operator;
+    if task_operator != Address::ZERO {
+        counter!("task_preflight_failed", "check" => "task_operator").increment(1);
+        return Err(eyre::eyre!(
+            "Task {} already has a receipt from {task_operator}",
+            task.task_id
+        ));
+    }
+
+    if kuda_instance
+        .isOperatorJailed(operator_address)
+        .call()
+        .await?
+        ._0
+    {
+        counter!("task_preflight_failed", "check" => "jailed").increment(1);
+        return Err(eyre::eyre!("Operator {operator_address} is jailed"));
+    }
+
+    let client_balance = kuda_instance
+        .kudaAccount(task.client_address, task.reward_token)
+        .call()
+        .await?
+        .balance;
+    if client_balance < task.reward_amount {
+        counter!("task_preflight_failed", "check" => "client_balance").increment(1);
+        return Err(eyre::eyre!(
+            "Client balance: {client_balance} is less than reward amount: {}",
+            task.reward_amount
+        ));
+    }
+
+    Ok(())
+}
+
+/// Posts a task's blob to its DA layer, retrying until its deadline
+async fn post_blob<T: Transport + Clone, P: Provider<T>>(
+    context: &TaskContext<T, P>,
+    task: &TaskResponsibility,
+) -> eyre::Result<DaReceipt> {
+    let retry_config = &context.retry_config;
+    let blob_data = BlobData::from_str(&task.data)?;
+    let deadline = retry_config.deadline_for(task.submission_time.saturating_to());
+    let label = format!("{} submission", task.da_layer);
+    let backend = context.da_registry.get(task.da_layer)?;
+    let da_receipt = retry(retry_config, deadline, &label, || async {
+        let da_receipt = backend.submit(&task.commitment, blob_data.clone()).await?;
+        if context.verify_posts {
+            verify_post(context, &da_receipt, &blob_data).await?;
+        }
+        Ok(da_receipt)
+    })
+    .await?;
+    context
+        .journal
+        .record_or_log(
+            task.task_id,
+            TaskEvent::BlobSubmitted {
+                receipt: da_receipt.clone(),
+            },
+        )
+        .await;
+
+    Ok(da_receipt)
+}
+
+/// Reads a posted blob back from its DA layer. Failing makes the post be retried, rather than
+/// committing to a receipt we could not defend in a challenge
+async fn verify_post<T: Transport + Clone, P: Provider<T>>(
+    context: &TaskContext<T, P>,
+    da_receipt: &DaReceipt,
+    blob_data: &BlobData,
+) -> eyre::Result<()> {
+    let result = match context.da_registry.backend_for(da_receipt) {
+        Ok(backend) => backend.verify_post(da_receipt, blob_data).await,
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        counter!("blob_verification_failed", "da_layer" => da_receipt.da_layer().to_string())
+            .increment(1);
+    }
+    result.wrap_err("Posted blob could not be verified")
+}
+
+/// What an earlier delivery of a task already achieved
+enum PriorSubmission {
+    /// The receipt is on-chain, there is nothing left to do
+    Complete,
+    /// The blob was posted but its receipt never made it on-chain
+    BlobPosted(DaReceipt),
+}
+
+/// Looks up the local and on-chain state of a task so that it is never posted twice
+async fn prior_submission<T: Transport + Clone, P: Provider<T>>(
+    kuda_instance: &KudaInstance<T, P>,
+    journal: &Journal,
+    task_id: Uuid,
+    operator_address: Address,
+) -> eyre::Result<Option<PriorSubmission>> {
+    let record = journal.task(&task_id).await.unwrap_or_default();
+    if record.is_receipt_submitted() {
+        return Ok(Some(PriorSubmission::Complete));
+    }
+
+    let task_id_bytes = FixedBytes::from(task_id.as_bytes());
+    let task_operator = kuda_instance
+        .taskOperator(task_id_bytes)
+        .call()
+        .await?
+        .operator;
+    if task_operator == operator_address {
         let receipt = kuda_instance
-            .submitReceipt(
-                Signer::address(&*operator_signer),
-                FixedBytes::from(task.task_id.as_bytes()),
-                Bytes::copy_from_slice(&signature.as_bytes()),
-                task.commitment.clone(),
-                context,
-                task.da_layer.into(),
-                task.submission_time,
-                task.client_address,
-                task.reward_token,
-                task.reward_amount,
-            )
-            .send()
+            .submittedReceipt(task_id_bytes)
+            .call()
             .await?
-            .get_receipt()
-            .await?;
+            .receipt;
+        tracing::info!("Found receipt of task {task_id} on-chain: {receipt}");
+        journal
+            .record_or_log(task_id, TaskEvent::ReceiptFound { receipt })
+            .await;
+        return Ok(Some(PriorSubmission::Complete));
+    }
+    if task_operator != Address::ZERO {
+        return Err(eyre::eyre!(
+            "Receipt of task {task_id} was already submitted by {task_operator}"
+        ));
+    }
 
-        tracing::info!(
-            "Submitted receipt with tx hash: {}",
-            receipt.transaction_hash
+    Ok(record.receipt.map(PriorSubmission::BlobPosted))
+}
+
+/// Submits the on-chain receipt for a task whose blob has been posted to the DA layer
+async fn submit_receipt<T: Transport + Clone, P: Provider<T>>(
+    kuda_instance: &KudaInstance<T, P>,
+    da_registry: &DaRegistry,
+    operator_address: Address,
+    task_id: Uuid,
+    assignment: &TaskAssignment,
+    da_receipt: &DaReceipt,
+) -> eyre::Result<TxHash> {
+    let signature = Signature::from_str(&assignment.signature)?;
+    let context = da_registry.backend_for(da_receipt)?.context(da_receipt)?;
+
+    let receipt = kuda_instance
+        .submitReceipt(
+            operator_address,
+            FixedBytes::from(task_id.as_bytes()),
+            Bytes::copy_from_slice(&signature.as_bytes()),
+            assignment.commitment.clone(),
+            context,
+            assignment.da_layer.into(),
+            assignment.submission_time,
+            assignment.client_address,
+            assignment.reward_token,
+            assignment.reward_amount,
+        )
+        .send()
+        .await?
+        .get_receipt()
+        .await?;
+
+    tracing::info!(
+        "Submitted receipt with tx hash: {}",
+        receipt.transaction_hash
+    );
+    Ok(receipt.transaction_hash)
+}
+
+/// Submits the receipts of tasks whose blob was posted before the operator last stopped
+pub async fn resubmit_pending_receipts<T: Transport + Clone, P: Provider<T>>(
+    kuda_instance: &KudaInstance<T, P>,
+    da_registry: &DaRegistry,
+    operator_address: Address,
+    journal: &Journal,
+) {
+    for (task_id, task) in journal.pending_receipts().await {
+        let (Some(assignment), Some(da_receipt)) = (task.assignment, task.receipt) else {
+            continue;
+        };
+        match prior_submission(kuda_instance, journal, task_id, operator_address).await {
+            Ok(Some(PriorSubmission::Complete)) => continue,
+            Ok(_) => {}
+            Err(e) => {
+                tracing::error!("Not resubmitting receipt for task {task_id}: {e:?}");
+                continue;
+            }
+        }
+        tracing::info!("Resubmitting receipt for task {task_id}");
+        match submit_receipt(
+            kuda_instance,
+            da_registry,
+            operator_address,
+            task_id,
+            &assignment,
+            &da_receipt,
+        )
+        .await
+        {
+            Ok(tx_hash) => {
+                journal
+                    .record_or_log(task_id, TaskEvent::ReceiptMined { tx_hash })
+                    .await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to resubmit receipt for task {task_id}: {e:?}");
+                journal
+                    .record_or_log(
+                        task_id,
+                        TaskEvent::Failed {
+                            error: e.to_string(),
+                        },
+                    )
+                    .await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+    use celestia_types::{nmt::Namespace, Commitment};
+
+    use super::*;
+    use crate::{
+        da::{celestia::CelestiaReceipt, registry::tests::FakeBackend, LayerReceipt},
+        socketio::model::DaLayer,
+    };
+
+    #[tokio::test]
+    async fn test_journaled_receipt() {
+        let path = std::env::temp_dir().join(format!("kuda-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Journal::open(&path).await.unwrap();
+        let mut da_registry = DaRegistry::default();
+        da_registry.register(FakeBackend);
+        let operator_address = Address::repeat_byte(1);
+        let task_id = Uuid::new_v4();
+        let receipt = || journaled_receipt(&journal, &da_registry, operator_address, task_id);
+
+        assert_eq!(receipt().await.unwrap(), None);
+
+        let assignment = TaskAssignment {
+            commitment: "commitment".to_string(),
+            da_layer: DaLayer::Celestia,
+            signature: String::new(),
+            submission_time: U256::ZERO,
+            client_address: Address::ZERO,
+            reward_token: Address::ZERO,
+            reward_amount: U256::ZERO,
+        };
+        let da_receipt = CelestiaReceipt {
+            height: 1,
+            commitment: Commitment([0; 32]),
+            namespace: Namespace::new_v0(&[1]).unwrap(),
+        }
+        .into_receipt();
+        journal
+            .record(task_id, TaskEvent::Assigned(assignment))
+            .await
+            .unwrap();
+        journal
+            .record(
+                task_id,
+                TaskEvent::BlobSubmitted {
+                    receipt: da_receipt.clone(),
+                },
+            )
+            .await
+            .unwrap();
+        // Still being submitted
+        assert_eq!(receipt().await.unwrap(), None);
+
+        let tx_hash = TxHash::repeat_byte(2);
+        journal
+            .record(task_id, TaskEvent::ReceiptMined { tx_hash })
+            .await
+            .unwrap();
+        assert_eq!(
+            receipt().await.unwrap(),
+            Some(TaskReceipt {
+                task_id,
+                operator_address,
+                da_layer: DaLayer::Celestia,
+                commitment: "commitment".to_string(),
+                context: da_registry
+                    .backend_for(&da_receipt)
+                    .unwrap()
+                    .context(&da_receipt)
+                    .unwrap(),
+                tx_hash: Some(tx_hash),
+            })
         );
+
+        tokio::fs::remove_file(&path).await.unwrap();
     }
-    Ok(())
 }
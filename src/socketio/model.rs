@@ -1,6 +1,7 @@
 use std::fmt::Display;
 
-use alloy::primitives::{Address, U256};
+use alloy::primitives::{Address, Bytes, TxHash, U256};
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -23,6 +24,20 @@ pub struct PostingInterest {
     pub da_layer: DaLayer,
 }
 
+/// The receipt we submitted for a task, sent back to the aggregator that assigned it
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskReceipt {
+    pub task_id: Uuid,
+    pub operator_address: Address,
+    pub da_layer: DaLayer,
+    pub commitment: String,
+    /// Context of the DA receipt, as submitted on-chain
+    pub context: Bytes,
+    /// The `submitReceipt` transaction, unknown if the receipt was only found on-chain
+    pub tx_hash: Option<TxHash>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TaskResponsibility {
@@ -37,12 +52,16 @@ pub struct TaskResponsibility {
     pub reward_amount: U256,
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, ValueEnum)]
 pub enum DaLayer {
     #[serde(rename = "Celestia")]
     Celestia,
     #[serde(rename = "4844")]
     Eip4844,
+    #[serde(rename = "Avail")]
+    Avail,
+    #[serde(rename = "EigenDA")]
+    EigenDa,
 }
 
 impl From<DaLayer> for u8 {
@@ -50,6 +69,22 @@ impl From<DaLayer> for u8 {
         match value {
             DaLayer::Celestia => 0,
             DaLayer::Eip4844 => 1,
+            DaLayer::Avail => 2,
+            DaLayer::EigenDa => 3,
+        }
+    }
+}
+
+impl TryFrom<u8> for DaLayer {
+    type Error = eyre::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(DaLayer::Celestia),
+            1 => Ok(DaLayer::Eip4844),
+            2 => Ok(DaLayer::Avail),
+            3 => Ok(DaLayer::EigenDa),
+            _ => Err(eyre::eyre!("Unknown DA layer {value}")),
         }
     }
 }
@@ -59,6 +94,8 @@ impl Display for DaLayer {
         match self {
             DaLayer::Celestia => write!(f, "Celestia"),
             DaLayer::Eip4844 => write!(f, "4844"),
+            DaLayer::Avail => write!(f, "Avail"),
+            DaLayer::EigenDa => write!(f, "EigenDA"),
         }
     }
 }
@@ -108,4 +145,23 @@ mod tests {
             vec![DaLayer::Celestia, DaLayer::Eip4844]
         );
     }
+
+    #[test]
+    fn test_da_layer_wire_format() {
+        for (da_layer, name, id) in [
+            (DaLayer::Celestia, "Celestia", 0),
+            (DaLayer::Eip4844, "4844", 1),
+            (DaLayer::Avail, "Avail", 2),
+            (DaLayer::EigenDa, "EigenDA", 3),
+        ] {
+            assert_eq!(serde_json::to_value(da_layer).unwrap(), json!(name));
+            assert_eq!(
+                serde_json::from_value::<DaLayer>(json!(name)).unwrap(),
+                da_layer
+            );
+            assert_eq!(u8::from(da_layer), id);
+            assert_eq!(DaLayer::try_from(id).unwrap(), da_layer);
+        }
+        assert!(DaLayer::try_from(4).is_err());
+    }
 }
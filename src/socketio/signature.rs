@@ -0,0 +1,235 @@
+use std::str::FromStr;
+
+use alloy::{
+    primitives::{keccak256, Address, Bytes, FixedBytes, B256},
+    providers::Provider,
+    signers::Signature,
+    transports::Transport,
+};
+
+use super::model::{DaLayer, TaskResponsibility};
+use crate::contracts::kuda::Kuda::{KudaErrors, KudaInstance};
+
+/// Where the address allowed to sign task assignments comes from
+#[derive(Debug, Clone, Copy)]
+pub enum AggregatorSigner {
+    /// Recover the signer locally and compare it with this address
+    Address(Address),
+    /// Let the Kuda contract check the signature in a simulated `submitReceipt`
+    OnChain,
+}
+
+/// Hash of the task fields the aggregator signs when assigning a task to `operator`,
+/// packed in the order `submitReceipt` takes them
+pub fn task_hash(task: &TaskResponsibility, operator: Address) -> B256 {
+    let mut message = Vec::new();
+    message.extend_from_slice(operator.as_slice());
+    message.extend_from_slice(task.task_id.as_bytes());
+    message.extend_from_slice(task.commitment.as_bytes());
+    message.push(task.da_layer.into());
+    message.extend_from_slice(&task.submission_time.to_be_bytes::<32>());
+    message.extend_from_slice(task.client_address.as_slice());
+    message.extend_from_slice(task.reward_token.as_slice());
+    message.extend_from_slice(&task.reward_amount.to_be_bytes::<32>());
+    keccak256(message)
+}
+
+/// Recovers the address that signed the assignment of `task` to `operator`
+pub fn recover_aggregator(task: &TaskResponsibility, operator: Address) -> eyre::Result<Address> {
+    let signature = Signature::from_str(&task.signature)?;
+    Ok(signature.recover_address_from_msg(task_hash(task, operator))?)
+}
+
+/// Checks that `task` was signed by the aggregator before we spend anything posting it
+pub async fn verify_aggregator_signature<T: Transport + Clone, P: Provider<T>>(
+    task: &TaskResponsibility,
+    operator: Address,
+    aggregator_signer: AggregatorSigner,
+    kuda_instance: &KudaInstance<T, P>,
+) -> eyre::Result<()> {
+    match aggregator_signer {
+        AggregatorSigner::Address(aggregator) => {
+            let signer = recover_aggregator(task, operator)?;
+            if signer != aggregator {
+                return Err(eyre::eyre!(
+                    "Task {} is signed by {signer}, expected aggregator {aggregator}",
+                    task.task_id
+                ));
+            }
+        }
+        AggregatorSigner::OnChain => {
+            // The contract only decodes the DA layers it has a verifier for, so a task on any
+            // other layer reverts before its signature is checked
+            if !matches!(task.da_layer, DaLayer::Celestia | DaLayer::Eip4844) {
+                return Err(eyre::eyre!(
+                    "Task {} is on {}, whose signatures the Kuda contract can't check",
+                    task.task_id,
+                    task.da_layer
+                ));
+            }
+            let signature = Signature::from_str(&task.signature)?;
+            let result = kuda_instance
+                .submitReceipt(
+                    operator,
+                    FixedBytes::from(task.task_id.as_bytes()),
+                    Bytes::copy_from_slice(&signature.as_bytes()),
+                    task.commitment.clone(),
+                    Bytes::new(),
+                    task.da_layer.into(),
+                    task.submission_time,
+                    task.client_address,
+                    task.reward_token,
+                    task.reward_amount,
+                )
+                .from(operator)
+                .call()
+                .await;
+            // Other reverts may depend on the DA receipt we don't have yet, so only the
+            // signature errors are conclusive
+            if let Err(alloy::contract::Error::TransportError(e)) = result {
+                let error = e
+                    .as_error_resp()
+                    .and_then(|payload| payload.as_decoded_error::<KudaErrors>(true));
+                let reason = match error {
+                    Some(KudaErrors::SignatureVerifactionFailed(_)) => {
+                        "signature verification failed"
+                    }
+                    Some(KudaErrors::NotAggregator(_)) => "not signed by the aggregator",
+                    _ => return Ok(()),
+                };
+                return Err(eyre::eyre!(
+                    "Task {} has an invalid aggregator signature: {reason}",
+                    task.task_id
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::{
+        primitives::U256,
+        signers::{local::PrivateKeySigner, SignerSync},
+        sol_types::{sol_data, SolType},
+    };
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_recover_aggregator() {
+        let aggregator = PrivateKeySigner::random();
+        let operator = Address::repeat_byte(1);
+        let mut task = TaskResponsibility {
+            task_id: Uuid::new_v4(),
+            data: String::new(),
+            commitment: "0x1234".to_string(),
+            da_layer: DaLayer::Celestia,
+            signature: String::new(),
+            submission_time: U256::from(1_700_000_000),
+            client_address: Address::repeat_byte(2),
+            reward_token: Address::repeat_byte(3),
+            reward_amount: U256::from(100),
+        };
+        let signature = aggregator
+            .sign_message_sync(task_hash(&task, operator).as_slice())
+            .unwrap();
+        task.signature = hex::encode(signature.as_bytes());
+
+        assert_eq!(
+            recover_aggregator(&task, operator).unwrap(),
+            aggregator.address()
+        );
+        assert_ne!(
+            recover_aggregator(&task, Address::repeat_byte(4)).unwrap(),
+            aggregator.address()
+        );
+
+        task.reward_amount = U256::from(1000);
+        assert_ne!(
+            recover_aggregator(&task, operator).unwrap(),
+            aggregator.address()
+        );
+    }
+
+    /// Pins the signed layout to Solidity's `abi.encodePacked` of the `submitReceipt` fields,
+    /// with a signature by the first Anvil development key
+    #[test]
+    fn test_task_hash_vector() {
+        let operator = Address::from_str("0x90F79bf6EB2c4f870365E785982E1f101E93b906").unwrap();
+        let task = TaskResponsibility {
+            task_id: Uuid::from_str("9f1b5a5e-3c2d-4b8e-a6f0-1d2c3b4a5968").unwrap(),
+            data: String::new(),
+            commitment: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string(),
+            da_layer: DaLayer::Eip4844,
+            signature: "0x77f461698029e3998c351874f1d72ea25995b35cd22d8921edf5ab050b99a3f011f6f86cf553d13a2efd6841118f6f901974beb204651c3fd6b4e94edb2a0a4c1b".to_string(),
+            submission_time: U256::from(1_717_171_717),
+            client_address: Address::from_str("0x70997970C51812dc3A010C7d01b50e0d17dc79C8")
+                .unwrap(),
+            reward_token: Address::from_str("0x3C44CdDdB6a900fa2b585dd299e03d12FA4293BC")
+                .unwrap(),
+            reward_amount: U256::from(10).pow(U256::from(18)),
+        };
+
+        let packed = <(
+            sol_data::Address,
+            sol_data::FixedBytes<16>,
+            sol_data::String,
+            sol_data::Uint<8>,
+            sol_data::Uint<256>,
+            sol_data::Address,
+            sol_data::Address,
+            sol_data::Uint<256>,
+        )>::abi_encode_packed(&(
+            operator,
+            FixedBytes::<16>::from(task.task_id.as_bytes()),
+            task.commitment.clone(),
+            u8::from(task.da_layer),
+            task.submission_time,
+            task.client_address,
+            task.reward_token,
+            task.reward_amount,
+        ));
+        assert_eq!(task_hash(&task, operator), keccak256(packed));
+        assert_eq!(
+            task_hash(&task, operator),
+            B256::from_str("0xd33113da3f4d339c05d1d2b5b7b7320172aea85dcf1c661e780ad81194622bd9")
+                .unwrap()
+        );
+        assert_eq!(
+            recover_aggregator(&task, operator).unwrap(),
+            Address::from_str("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266").unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_on_chain_rejects_layers_without_verifier() {
+        // Never reached, the task is rejected before the contract is called
+        let provider =
+            alloy::providers::ProviderBuilder::new().on_http("http://127.0.0.1:1".parse().unwrap());
+        let kuda_instance = KudaInstance::new(Address::ZERO, provider);
+        let task = TaskResponsibility {
+            task_id: Uuid::new_v4(),
+            data: String::new(),
+            commitment: String::new(),
+            da_layer: DaLayer::Avail,
+            signature: String::new(),
+            submission_time: U256::ZERO,
+            client_address: Address::ZERO,
+            reward_token: Address::ZERO,
+            reward_amount: U256::ZERO,
+        };
+
+        let error = verify_aggregator_signature(
+            &task,
+            Address::ZERO,
+            AggregatorSigner::OnChain,
+            &kuda_instance,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.to_string().contains("can't check"));
+    }
+}
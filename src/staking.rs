@@ -0,0 +1,233 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use alloy::{
+    primitives::{aliases::U48, utils::parse_units, Address},
+    providers::Provider,
+    transports::Transport,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    contracts::{
+        core::Operator::{QueuedStakeUpdate, StakeUpdateRequest},
+        vault::Vault::VaultInstance,
+    },
+    operator::Operator,
+};
+
+/// A stake update that was requested and has to be finalized once Core's delay has passed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingStakeUpdate {
+    pub nonce: u64,
+    pub start_timestamp: u64,
+    pub operator: Address,
+    pub vault: Address,
+    pub dss: Address,
+    pub to_stake: bool,
+}
+
+impl From<&QueuedStakeUpdate> for PendingStakeUpdate {
+    fn from(update: &QueuedStakeUpdate) -> Self {
+        PendingStakeUpdate {
+            nonce: update.nonce.to(),
+            start_timestamp: update.startTimestamp.to(),
+            operator: update.operator,
+            vault: update.updateRequest.vault,
+            dss: update.updateRequest.dss,
+            to_stake: update.updateRequest.toStake,
+        }
+    }
+}
+
+impl From<&PendingStakeUpdate> for QueuedStakeUpdate {
+    fn from(update: &PendingStakeUpdate) -> Self {
+        QueuedStakeUpdate {
+            nonce: U48::from(update.nonce),
+            startTimestamp: U48::from(update.start_timestamp),
+            operator: update.operator,
+            updateRequest: StakeUpdateRequest {
+                vault: update.vault,
+                dss: update.dss,
+                toStake: update.to_stake,
+            },
+        }
+    }
+}
+
+/// Pending stake updates by vault, persisted as JSON so they survive between invocations
+pub struct PendingStakeUpdates {
+    path: PathBuf,
+    updates: BTreeMap<Address, PendingStakeUpdate>,
+}
+
+impl PendingStakeUpdates {
+    pub async fn load(path: &Path) -> eyre::Result<Self> {
+        let updates = if tokio::fs::try_exists(path).await? {
+            serde_json::from_slice(&tokio::fs::read(path).await?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self {
+            path: path.to_path_buf(),
+            updates,
+        })
+    }
+
+    pub async fn save(&self) -> eyre::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&self.path, serde_json::to_vec_pretty(&self.updates)?).await?;
+        Ok(())
+    }
+
+    pub fn insert(&mut self, update: PendingStakeUpdate) {
+        self.updates.insert(update.vault, update);
+    }
+
+    pub fn remove(&mut self, vault: &Address) -> Option<PendingStakeUpdate> {
+        self.updates.remove(vault)
+    }
+
+    pub fn get(&self, vault: &Address) -> Option<&PendingStakeUpdate> {
+        self.updates.get(vault)
+    }
+
+    pub fn vaults(&self) -> Vec<Address> {
+        self.updates.keys().copied().collect()
+    }
+}
+
+pub async fn create_vault<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    asset: Address,
+    name: String,
+    symbol: String,
+    vault_impl: Address,
+) -> eyre::Result<()> {
+    let (vault, tx_hash) = operator
+        .deploy_vault(asset, name, symbol, vault_impl)
+        .await?;
+    println!("Vault {vault} created with tx hash: {tx_hash}");
+    Ok(())
+}
+
+/// Deposits `amount`, in whole units of the vault's asset, to `vault`
+pub async fn deposit<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    vault: Address,
+    amount: &str,
+) -> eyre::Result<()> {
+    let decimals = VaultInstance::new(vault, operator.provider.clone())
+        .decimals()
+        .call()
+        .await?
+        ._0;
+    let amount = parse_units(amount, decimals)?.into();
+    let tx_hash = operator.deposit(vault, amount).await?;
+    println!("Deposited to vault {vault} with tx hash: {tx_hash}");
+    Ok(())
+}
+
+/// Requests staking or unstaking `vault` and records the update so that it can be finalized
+pub async fn request_stake_update<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    pending_path: &Path,
+    vault: Address,
+    to_stake: bool,
+) -> eyre::Result<()> {
+    let mut pending = PendingStakeUpdates::load(pending_path).await?;
+    if let Some(update) = pending.get(&vault) {
+        return Err(eyre::eyre!(
+            "Vault {vault} already has a pending stake update with nonce {}, finalize it first",
+            update.nonce
+        ));
+    }
+
+    let (queued_stake_update, tx_hash) = operator.request_stake_update(vault, to_stake).await?;
+    let update = PendingStakeUpdate::from(&queued_stake_update);
+    println!(
+        "Stake update for vault {vault} requested with tx hash: {tx_hash}\nNonce: {}\nStart timestamp: {}",
+        update.nonce, update.start_timestamp
+    );
+    pending.insert(update);
+    pending.save().await?;
+    println!("Run `kuda-operator vault finalize` once the stake update delay has passed");
+    Ok(())
+}
+
+/// Finalizes the pending stake update of `vault`, or of every vault if not set
+pub async fn finalize_stake_updates<T: Transport + Clone, P: Provider<T> + Clone>(
+    operator: &Operator<T, P>,
+    pending_path: &Path,
+    vault: Option<Address>,
+) -> eyre::Result<()> {
+    let mut pending = PendingStakeUpdates::load(pending_path).await?;
+    let vaults = match vault {
+        Some(vault) => vec![vault],
+        None => pending.vaults(),
+    };
+    if vaults.is_empty() {
+        println!("No pending stake updates");
+        return Ok(());
+    }
+
+    for vault in vaults {
+        let update = pending
+            .get(&vault)
+            .ok_or_else(|| eyre::eyre!("No pending stake update for vault {vault}"))?;
+        match operator.finalize_stake_update(update.into()).await {
+            Ok(tx_hash) => {
+                println!("Stake update for vault {vault} finalized with tx hash: {tx_hash}");
+                pending.remove(&vault);
+                pending.save().await?;
+            }
+            Err(e) => println!("Could not finalize stake update for vault {vault}: {e}"),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pending_stake_updates() {
+        let path = std::env::temp_dir().join(format!("kuda-stake-updates-{}.json", Uuid::new_v4()));
+        let update = PendingStakeUpdate {
+            nonce: 3,
+            start_timestamp: 1_700_000_000,
+            operator: Address::repeat_byte(1),
+            vault: Address::repeat_byte(2),
+            dss: Address::repeat_byte(3),
+            to_stake: true,
+        };
+        assert_eq!(
+            PendingStakeUpdate::from(&QueuedStakeUpdate::from(&update)),
+            update
+        );
+
+        let mut pending = PendingStakeUpdates::load(&path).await.unwrap();
+        pending.insert(update.clone());
+        pending.save().await.unwrap();
+
+        let mut pending = PendingStakeUpdates::load(&path).await.unwrap();
+        assert_eq!(pending.vaults(), vec![update.vault]);
+        assert_eq!(pending.remove(&update.vault), Some(update));
+        pending.save().await.unwrap();
+        assert!(PendingStakeUpdates::load(&path)
+            .await
+            .unwrap()
+            .vaults()
+            .is_empty());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}
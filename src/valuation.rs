@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use alloy::primitives::Address;
+use serde::Serialize;
+
+use crate::{
+    operator::Vault,
+    price::{whole_units, Asset, PriceSource},
+};
+
+/// Value of the assets in a vault
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VaultValuation {
+    pub usd: f64,
+    pub eth: Option<f64>,
+}
+
+/// Value of the operator stake. Vaults whose asset has no price are left out of the totals
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StakeValuation {
+    pub vaults: HashMap<Address, VaultValuation>,
+    pub unpriced_vaults: Vec<Address>,
+    pub total_usd: f64,
+    pub total_eth: Option<f64>,
+}
+
+/// Values `stake` in USD through `price_source`, and in ETH if ETH has a price
+pub async fn value_stake(
+    stake: &HashMap<Address, Vault>,
+    price_source: &PriceSource,
+) -> StakeValuation {
+    let eth_usd = match price_source.usd_price(&Asset::Eth).await {
+        Ok(price) if price > 0.0 => Some(price),
+        Ok(_) => None,
+        Err(e) => {
+            tracing::warn!("Could not price ETH: {e:?}");
+            None
+        }
+    };
+
+    let mut valuation = StakeValuation::default();
+    for (vault_address, vault) in stake {
+        match price_source.usd_price(&Asset::Token(vault.asset)).await {
+            Ok(asset_usd) => {
+                let usd = whole_units(vault.amount, vault.decimals) * asset_usd;
+                valuation.total_usd += usd;
+                valuation.vaults.insert(
+                    *vault_address,
+                    VaultValuation {
+                        usd,
+                        eth: eth_usd.map(|eth_usd| usd / eth_usd),
+                    },
+                );
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Could not price asset {} of vault {vault_address}: {e:?}",
+                    vault.asset
+                );
+                valuation.unpriced_vaults.push(*vault_address);
+            }
+        }
+    }
+    valuation.unpriced_vaults.sort();
+    valuation.total_eth = eth_usd.map(|eth_usd| valuation.total_usd / eth_usd);
+    valuation
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy::primitives::U256;
+
+    use super::*;
+    use crate::price::AssetPrice;
+
+    #[tokio::test]
+    async fn test_value_stake() {
+        let vault = |asset: Address, decimals: u8, amount: u64| Vault {
+            symbol: "kTKN".to_string(),
+            name: "Vault".to_string(),
+            asset,
+            decimals,
+            amount: U256::from(amount),
+        };
+        let usdc = Address::repeat_byte(0xa);
+        let weth = Address::repeat_byte(0xb);
+        let unknown = Address::repeat_byte(0xc);
+        let stake = HashMap::from([
+            (Address::repeat_byte(1), vault(usdc, 6, 3_000_000_000)),
+            (
+                Address::repeat_byte(2),
+                vault(weth, 18, 2_000_000_000_000_000_000),
+            ),
+            (Address::repeat_byte(3), vault(unknown, 18, 1)),
+        ]);
+        let prices = [
+            AssetPrice {
+                asset: Asset::Eth,
+                usd: 3000.0,
+            },
+            AssetPrice {
+                asset: Asset::Token(usdc),
+                usd: 1.0,
+            },
+            AssetPrice {
+                asset: Asset::Token(weth),
+                usd: 3000.0,
+            },
+        ];
+
+        let valuation = value_stake(&stake, &PriceSource::from_prices(&prices)).await;
+        assert_eq!(valuation.total_usd, 9000.0);
+        assert_eq!(valuation.total_eth, Some(3.0));
+        assert_eq!(valuation.vaults[&Address::repeat_byte(1)].eth, Some(1.0));
+        assert_eq!(valuation.unpriced_vaults, vec![Address::repeat_byte(3)]);
+
+        let valuation = value_stake(&stake, &PriceSource::from_prices(&prices[1..])).await;
+        assert_eq!(valuation.total_usd, 9000.0);
+        assert_eq!(valuation.total_eth, None);
+    }
+}
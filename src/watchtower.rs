@@ -0,0 +1,503 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{Address, FixedBytes, U256},
+    providers::Provider,
+    rpc::types::Log,
+    sol_types::SolCall,
+    transports::Transport,
+};
+use eyre::OptionExt;
+use metrics::counter;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use uuid::Uuid;
+
+use crate::{
+    contracts::kuda::Kuda::{submitReceiptCall, KudaInstance, ReceiptSubmitted},
+    da::{registry::DaRegistry, DaReceipt},
+    journal::{BlockCursor, Journal, TaskEvent},
+    socketio::model::DaLayer,
+};
+
+/// Bond and period of the Kuda challenge game, read once at startup
+#[derive(Debug, Clone, Copy)]
+struct ChallengeTerms {
+    bond: U256,
+    challenge_period: U256,
+}
+
+/// A receipt submitted by another operator whose blob still has to be found
+struct PeerReceipt {
+    task_id: FixedBytes<16>,
+    call: submitReceiptCall,
+    receipt: DaReceipt,
+    /// Block the receipt was submitted in
+    block_number: u64,
+    /// Timestamp after which the receipt can no longer be challenged
+    deadline: U256,
+}
+
+/// Checks the receipts other operators submit against our own DA endpoints and challenges the
+/// ones whose blob is missing. The settlement keeper finishes those challenges to collect
+pub struct Watchtower<T: Transport + Clone, P: Provider<T>> {
+    pub operator_address: Address,
+    pub kuda_instance: Arc<KudaInstance<T, P>>,
+    pub da_registry: Arc<DaRegistry>,
+    pub journal: Arc<Journal>,
+    pub poll_interval: Duration,
+    /// Block to resume indexing receipts from after a restart, which is never past a pending
+    /// receipt so that it gets indexed again
+    cursor: BlockCursor,
+    pending: Mutex<Vec<PeerReceipt>>,
+}
+
+impl<T: Transport + Clone, P: Provider<T>> Watchtower<T, P> {
+    pub fn new(
+        operator_address: Address,
+        kuda_instance: Arc<KudaInstance<T, P>>,
+        da_registry: Arc<DaRegistry>,
+        journal: Arc<Journal>,
+        poll_interval: Duration,
+    ) -> Self {
+        Self {
+            operator_address,
+            kuda_instance,
+            da_registry,
+            cursor: journal.cursor("watchtower"),
+            journal,
+            poll_interval,
+            pending: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub async fn run(&self, cancellation_token: CancellationToken) -> eyre::Result<()> {
+        let terms = ChallengeTerms {
+            bond: self.kuda_instance.CHALLENGE_BOND().call().await?._0,
+            challenge_period: self.kuda_instance.CHALLENGE_PERIOD().call().await?._0,
+        };
+        let mut from_block = match self.cursor.load().await? {
+            Some(block) => block,
+            None => self.kuda_instance.provider().get_block_number().await?,
+        };
+
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    tracing::info!("Watchtower cancelled");
+                    return Ok(());
+                }
+                _ = tokio::time::sleep(self.poll_interval) => {}
+            }
+
+            match self.poll(from_block, terms).await {
+                Ok(next_block) => from_block = next_block,
+                Err(e) => tracing::error!("Watchtower error: {e:?}"),
+            }
+        }
+    }
+
+    /// Indexes the receipts submitted from `from_block` up to the latest block and checks the
+    /// pending ones. Returns the block to resume from, which only moves past the new receipts
+    /// if all of them could be indexed
+    async fn poll(&self, from_block: u64, terms: ChallengeTerms) -> eyre::Result<u64> {
+        let latest_block = self
+            .kuda_instance
+            .provider()
+            .get_block_by_number(BlockNumberOrTag::Latest, false)
+            .await?
+            .ok_or_eyre("Could not get latest block")?;
+        let now = U256::from(latest_block.header.timestamp);
+        let latest_block = latest_block.header.number;
+
+        let mut next_block = from_block;
+        if latest_block >= from_block {
+            let events = self
+                .kuda_instance
+                .ReceiptSubmitted_filter()
+                .from_block(from_block)
+                .to_block(latest_block)
+                .query()
+                .await?;
+            let mut indexed = Vec::new();
+            for (event, log) in events {
+                if event.operator == self.operator_address {
+                    continue;
+                }
+                indexed.extend(self.index(&event, &log, terms).await?);
+            }
+            self.pending.lock().await.extend(indexed);
+            next_block = latest_block + 1;
+        }
+
+        self.check_pending(now, terms).await;
+
+        let cursor = self
+            .pending
+            .lock()
+            .await
+            .iter()
+            .map(|peer_receipt| peer_receipt.block_number)
+            .fold(next_block, u64::min);
+        // The new receipts are pending already, failing would index them twice
+        if let Err(e) = self.cursor.save(cursor).await {
+            tracing::error!("Failed to save watchtower cursor: {e:?}");
+        }
+        Ok(next_block)
+    }
+
+    /// Fetches the `submitReceipt` call behind a `ReceiptSubmitted` event. Fails if the node
+    /// could not answer, and returns `None` for receipts we can never check
+    async fn index(
+        &self,
+        event: &ReceiptSubmitted,
+        log: &Log,
+        terms: ChallengeTerms,
+    ) -> eyre::Result<Option<PeerReceipt>> {
+        let provider = self.kuda_instance.provider();
+        let tx_hash = log
+            .transaction_hash
+            .ok_or_eyre("No transaction hash in log")?;
+        let tx = provider
+            .get_transaction_by_hash(tx_hash)
+            .await?
+            .ok_or_eyre("Receipt transaction not found")?;
+        let block_number = log.block_number.ok_or_eyre("No block number in log")?;
+        let block = provider
+            .get_block_by_number(BlockNumberOrTag::Number(block_number), false)
+            .await?
+            .ok_or_eyre("Receipt block not found")?;
+
+        let (call, receipt) = match self.decode(event, &tx.input) {
+            Ok(decoded) => decoded,
+            Err(e) => {
+                tracing::warn!(
+                    "Could not index receipt of task {} by {} in tx {tx_hash}: {e:?}",
+                    Uuid::from_bytes(event.taskId.0),
+                    event.operator
+                );
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(PeerReceipt {
+            task_id: event.taskId,
+            call,
+            receipt,
+            block_number,
+            deadline: U256::from(block.header.timestamp) + terms.challenge_period,
+        }))
+    }
+
+    /// Decodes the DA receipt of a `submitReceipt` call
+    fn decode(
+        &self,
+        event: &ReceiptSubmitted,
+        input: &[u8],
+    ) -> eyre::Result<(submitReceiptCall, DaReceipt)> {
+        // Receipts submitted through another contract can't be decoded from the calldata
+        let call = submitReceiptCall::abi_decode(input, true)?;
+        if call.taskId != event.taskId {
+            return Err(eyre::eyre!("Transaction submits another task"));
+        }
+
+        let da_layer = DaLayer::try_from(call.daLayer)?;
+        let receipt = self
+            .da_registry
+            .get(da_layer)?
+            .receipt_from_context(&call.commitment, &call.context)?;
+        Ok((call, receipt))
+    }
+
+    /// Checks the inclusion of the pending receipts, keeping the ones we could not check or
+    /// challenge yet until their challenge period is over
+    async fn check_pending(&self, now: U256, terms: ChallengeTerms) {
+        let pending = std::mem::take(&mut *self.pending.lock().await);
+        let mut unchecked = Vec::new();
+        for peer_receipt in pending {
+            let task_id = Uuid::from_bytes(peer_receipt.task_id.0);
+            if now >= peer_receipt.deadline {
+                counter!("watchtower_receipt_checked", "result" => "expired").increment(1);
+                tracing::warn!("Challenge period of task {task_id} is over before it was checked");
+                continue;
+            }
+
+            let included = match self.da_registry.backend_for(&peer_receipt.receipt) {
+                Ok(backend) => backend.is_included(&peer_receipt.receipt).await,
+                Err(e) => Err(e),
+            };
+            match included {
+                Ok(true) => {
+                    counter!("watchtower_receipt_checked", "result" => "included").increment(1);
+                    tracing::debug!("Blob of task {task_id} is included");
+                }
+                Ok(false) => {
+                    counter!("watchtower_receipt_checked", "result" => "missing").increment(1);
+                    tracing::warn!(
+                        "Blob of task {task_id} by {} is missing from {}",
+                        peer_receipt.call.operator,
+                        peer_receipt.receipt.da_layer()
+                    );
+                    if let Err(e) = self.challenge(&peer_receipt, terms).await {
+                        counter!("watchtower_challenge_error").increment(1);
+                        tracing::error!(
+                            "Failed to challenge task {task_id}, retrying next poll: {e:?}"
+                        );
+                        unchecked.push(peer_receipt);
+                    }
+                }
+                Err(e) => {
+                    tracing::debug!("Could not check blob of task {task_id} yet: {e:?}");
+                    unchecked.push(peer_receipt);
+                }
+            }
+        }
+        self.pending.lock().await.extend(unchecked);
+    }
+
+    #[tracing::instrument(skip_all, fields(task_id = %Uuid::from_bytes(peer_receipt.task_id.0)))]
+    async fn challenge(
+        &self,
+        peer_receipt: &PeerReceipt,
+        terms: ChallengeTerms,
+    ) -> eyre::Result<()> {
+        let challenge = self
+            .kuda_instance
+            .challengeData(peer_receipt.task_id)
+            .call()
+            .await?;
+        if challenge.challenger != Address::ZERO {
+            tracing::info!("Task already challenged by {}", challenge.challenger);
+            return Ok(());
+        }
+
+        let call = &peer_receipt.call;
+        let tx_receipt = self
+            .kuda_instance
+            .createChallenge(
+                call.operator,
+                call.taskId,
+                call.aggregatorSignature.clone(),
+                call.commitment.clone(),
+                call.daLayer,
+                call.submissionTime,
+                call.clientAddress,
+                call.rewardToken,
+                call.rewardAmount,
+            )
+            .value(terms.bond)
+            .send()
+            .await?
+            .get_receipt()
+            .await?;
+        if !tx_receipt.status() {
+            return Err(eyre::eyre!(
+                "Challenge transaction {} reverted",
+                tx_receipt.transaction_hash
+            ));
+        }
+
+        counter!("watchtower_challenge_created").increment(1);
+        self.journal
+            .record_or_log(
+                Uuid::from_bytes(call.taskId.0),
+                TaskEvent::ChallengeOpened {
+                    operator: call.operator,
+                    tx_hash: tx_receipt.transaction_hash,
+                },
+            )
+            .await;
+        tracing::warn!(
+            "Challenged receipt of {} with tx hash: {}",
+            call.operator,
+            tx_receipt.transaction_hash
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy::{
+        primitives::Bytes,
+        providers::{ProviderBuilder, RootProvider},
+        transports::http::{Client, Http},
+    };
+    use celestia_types::{nmt::Namespace, Commitment};
+
+    use super::*;
+    use crate::{
+        da::{celestia::CelestiaReceipt, BlobData, Estimator, Health, Prover, Submitter, Verifier},
+        price::Asset,
+    };
+
+    /// A Celestia backend for which no blob is included
+    struct MissingBlobs;
+
+    impl Submitter for MissingBlobs {
+        type Receipt = CelestiaReceipt;
+
+        async fn submit(&self, _: &str, _: BlobData) -> eyre::Result<CelestiaReceipt> {
+            Err(eyre::eyre!("not posting"))
+        }
+    }
+
+    impl Verifier for MissingBlobs {
+        type Receipt = CelestiaReceipt;
+
+        async fn is_included(&self, _: &CelestiaReceipt) -> eyre::Result<bool> {
+            Ok(false)
+        }
+
+        async fn verify_post(&self, _: &CelestiaReceipt, _: &BlobData) -> eyre::Result<()> {
+            Err(eyre::eyre!("not posted"))
+        }
+    }
+
+    impl Prover for MissingBlobs {
+        type Receipt = CelestiaReceipt;
+
+        async fn inclusion_proof(&self, _: &CelestiaReceipt) -> eyre::Result<Bytes> {
+            Err(eyre::eyre!("not posted"))
+        }
+    }
+
+    impl Estimator for MissingBlobs {
+        const NATIVE_ASSET: Asset = Asset::Tia;
+
+        async fn estimate_cost(&self, _: u64) -> eyre::Result<U256> {
+            Ok(U256::ZERO)
+        }
+    }
+
+    impl Health for MissingBlobs {
+        async fn health(&self) -> eyre::Result<()> {
+            Ok(())
+        }
+    }
+
+    const TERMS: ChallengeTerms = ChallengeTerms {
+        bond: U256::ZERO,
+        challenge_period: U256::ZERO,
+    };
+
+    async fn watchtower(
+        da_registry: DaRegistry,
+    ) -> (
+        Watchtower<Http<Client>, RootProvider<Http<Client>>>,
+        PathBuf,
+    ) {
+        let path = std::env::temp_dir().join(format!("kuda-journal-{}.jsonl", Uuid::new_v4()));
+        let journal = Arc::new(Journal::open(&path).await.unwrap());
+        // Nothing listens there, so every call fails
+        let provider = ProviderBuilder::new().on_http("http://127.0.0.1:1".parse().unwrap());
+        let watchtower = Watchtower::new(
+            Address::repeat_byte(1),
+            Arc::new(KudaInstance::new(Address::ZERO, provider)),
+            Arc::new(da_registry),
+            journal,
+            Duration::from_secs(1),
+        );
+        (watchtower, path)
+    }
+
+    fn call(task_id: FixedBytes<16>, da_layer: u8) -> submitReceiptCall {
+        submitReceiptCall {
+            operator: Address::repeat_byte(2),
+            taskId: task_id,
+            aggregatorSignature: Bytes::new(),
+            commitment: String::new(),
+            context: Bytes::new(),
+            daLayer: da_layer,
+            submissionTime: U256::ZERO,
+            clientAddress: Address::ZERO,
+            rewardToken: Address::ZERO,
+            rewardAmount: U256::ZERO,
+        }
+    }
+
+    fn peer_receipt(task_id: u8, deadline: u64) -> PeerReceipt {
+        PeerReceipt {
+            task_id: FixedBytes([task_id; 16]),
+            call: call(FixedBytes([task_id; 16]), 0),
+            receipt: DaReceipt::Celestia(CelestiaReceipt {
+                height: 1,
+                commitment: Commitment([0; 32]),
+                namespace: Namespace::new_v0(&[1]).unwrap(),
+            }),
+            block_number: 1,
+            deadline: U256::from(deadline),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode() {
+        let (watchtower, path) = watchtower(DaRegistry::default()).await;
+        let event = ReceiptSubmitted {
+            taskId: FixedBytes([1; 16]),
+            operator: Address::repeat_byte(2),
+            receipt: FixedBytes::ZERO,
+        };
+
+        let other_task = call(FixedBytes([2; 16]), 0).abi_encode();
+        let error = watchtower.decode(&event, &other_task).err().unwrap();
+        assert!(error.to_string().contains("another task"));
+
+        let unknown_layer = call(event.taskId, 9).abi_encode();
+        assert!(watchtower.decode(&event, &unknown_layer).is_err());
+
+        // No backend is registered for Celestia
+        let unregistered_layer = call(event.taskId, 0).abi_encode();
+        let error = watchtower
+            .decode(&event, &unregistered_layer)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("No backend"));
+
+        assert!(watchtower.decode(&event, &[1, 2, 3]).is_err());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_pending() {
+        let (watchtower, path) = watchtower(DaRegistry::default()).await;
+        watchtower
+            .pending
+            .lock()
+            .await
+            .extend([peer_receipt(1, 100), peer_receipt(2, 200)]);
+
+        // The first receipt expired, the second can't be checked without a backend
+        watchtower.check_pending(U256::from(100), TERMS).await;
+        let pending = watchtower.pending.lock().await;
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].task_id, FixedBytes([2; 16]));
+        drop(pending);
+
+        watchtower.check_pending(U256::from(200), TERMS).await;
+        assert!(watchtower.pending.lock().await.is_empty());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_check_pending_keeps_failed_challenges() {
+        let mut da_registry = DaRegistry::default();
+        da_registry.register(MissingBlobs);
+        let (watchtower, path) = watchtower(da_registry).await;
+        watchtower.pending.lock().await.push(peer_receipt(1, 100));
+
+        // The blob is missing but the challenge can't be sent
+        watchtower.check_pending(U256::from(50), TERMS).await;
+        assert_eq!(watchtower.pending.lock().await.len(), 1);
+
+        watchtower.check_pending(U256::from(100), TERMS).await;
+        assert!(watchtower.pending.lock().await.is_empty());
+
+        tokio::fs::remove_file(&path).await.unwrap();
+    }
+}